@@ -0,0 +1,587 @@
+//Two pass assembler for the Cowgod mnemonics the disassembler prints. Besides instructions it takes
+//`label:` definitions, `NAME = expr` / `NAME equ expr` constants, `org`, `db` and `dw`, and operands
+//can be expressions over numbers, labels, constants and `$` (the current address). A disassembly
+//listing assembles back as it is, its address and bytes columns are skipped.
+//
+//    ; comments start with a semicolon
+//    start:  LD V0, SPEED * 2
+//            LD I, sprite
+//            DRW V0, V1, sprite_end - sprite
+//            JP $
+//    SPEED = 3
+//    sprite: db 0b11110000, 0x90, 0x90
+//    sprite_end:
+
+use std::collections::HashMap;
+use std::fmt;
+use crate::instruction::Instruction;
+
+const DEFAULT_ORIGIN: usize = 0x200;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmError {
+    //1 based source line
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+//binary starting at the first emitted address (0x200 unless an org comes before any code), ready for
+//Processor::load_rom. gaps left by org are zero filled
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let statements = parse(source)?;
+
+    //pass 1: addresses for labels, values for constants
+    let mut symbols: HashMap<String, i64> = HashMap::new();
+    let mut sizes = Vec::with_capacity(statements.len());
+    let mut address = DEFAULT_ORIGIN;
+    for statement in &statements {
+        let error = |message: String| AsmError { line: statement.line, message };
+        for label in &statement.labels {
+            define(&mut symbols, label, address as i64).map_err(error)?;
+        }
+        let size = statement.kind.size(&symbols, address);
+        match &statement.kind {
+            Kind::Constant(name, expression) => {
+                let value = evaluate(expression, &symbols, address).map_err(error)?;
+                define(&mut symbols, name, value).map_err(error)?;
+            }
+            Kind::Org(expression) => address = org(expression, &symbols, address).map_err(error)?,
+            _ => address += size,
+        }
+        sizes.push(size);
+        if address > 0x10000 {
+            return Err(error("program runs past the end of memory at 0xFFFF".to_string()));
+        }
+    }
+
+    //pass 2: encode with every symbol known
+    let mut output = Vec::new();
+    let mut base = None;
+    let mut address = DEFAULT_ORIGIN;
+    for (statement, size) in statements.iter().zip(sizes) {
+        let error = |message: String| AsmError { line: statement.line, message };
+        let bytes = match &statement.kind {
+            Kind::Constant(..) | Kind::Empty => continue,
+            Kind::Org(expression) => {
+                address = org(expression, &symbols, address).map_err(error)?;
+                continue;
+            }
+            Kind::Bytes(values) => values.iter().map(|value| byte(value, &symbols, address)).collect::<Result<Vec<u8>, String>>(),
+            Kind::Words(values) => values.iter()
+                .map(|value| word(value, &symbols, address).map(|word| word.to_be_bytes()))
+                .collect::<Result<Vec<[u8; 2]>, String>>().map(|words| words.concat()),
+            Kind::Instruction(mnemonic, operands) => encode(mnemonic, operands, &symbols, address)
+        }.map_err(error)?;
+
+        //only LD I can grow, when it names a label above 0xFFF defined further down
+        if bytes.len() != size {
+            return Err(error("LD I to a label above 0xFFF defined later in the file needs LD I, LONG".to_string()));
+        }
+        if bytes.is_empty() {
+            continue;
+        }
+        let base = *base.get_or_insert(address);
+        if address < base {
+            return Err(error(format!("org 0x{:X} is below the start of the program at 0x{:X}", address, base)));
+        }
+        let offset = address - base;
+        if output.len() < offset + bytes.len() {
+            output.resize(offset + bytes.len(), 0);
+        }
+        output[offset..offset + bytes.len()].copy_from_slice(&bytes);
+        address += bytes.len();
+    }
+    Ok(output)
+}
+
+struct Statement {
+    line: usize,
+    labels: Vec<String>,
+    kind: Kind,
+}
+
+enum Kind {
+    Empty,
+    Constant(String, String),
+    Org(String),
+    Bytes(Vec<String>),
+    Words(Vec<String>),
+    Instruction(String, Vec<String>),
+}
+
+impl Kind {
+    //bytes emitted, worked out in pass 1 before forward labels are known
+    fn size(&self, symbols: &HashMap<String, i64>, address: usize) -> usize {
+        match self {
+            Kind::Bytes(values) => values.len(),
+            Kind::Words(values) => values.len() * 2,
+            Kind::Instruction(mnemonic, operands) if long_load(mnemonic, operands, symbols, address) => 4,
+            Kind::Instruction(..) => 2,
+            _ => 0
+        }
+    }
+}
+
+fn parse(source: &str) -> Result<Vec<Statement>, AsmError> {
+    let mut statements = Vec::new();
+    for (number, line) in source.lines().enumerate() {
+        let line_number = number + 1;
+        let error = |message: String| AsmError { line: line_number, message };
+        let mut rest = skip_listing_columns(line.split(';').next().unwrap().trim());
+        let mut labels = Vec::new();
+        //any number of leading "name:" labels
+        while let Some((label, after)) = rest.split_once(':') {
+            let label = label.trim();
+            if !is_identifier(label) {
+                break;
+            }
+            labels.push(label.to_string());
+            rest = after.trim();
+        }
+
+        let (first, operands) = match rest.split_once(char::is_whitespace) {
+            Some((first, operands)) => (first, operands.trim()),
+            None => (rest, "")
+        };
+        let kind = if rest.is_empty() {
+            Kind::Empty
+        } else if let Some((name, expression)) = rest.split_once('=') {
+            Kind::Constant(identifier(name.trim()).map_err(error)?, expression.trim().to_string())
+        } else if let Some((_, expression)) = operands.split_once(char::is_whitespace).filter(|(directive, _)| directive.eq_ignore_ascii_case("equ")) {
+            Kind::Constant(identifier(first).map_err(error)?, expression.trim().to_string())
+        } else {
+            let list = || operands.split(',').map(|operand| operand.trim().to_string()).filter(|operand| !operand.is_empty()).collect::<Vec<_>>();
+            match first.to_uppercase().as_str() {
+                "ORG" => Kind::Org(operands.to_string()),
+                "DB" => Kind::Bytes(list()),
+                "DW" => Kind::Words(list()),
+                mnemonic => Kind::Instruction(mnemonic.to_string(), list())
+            }
+        };
+        if let Kind::Bytes(values) | Kind::Words(values) = &kind {
+            if values.is_empty() {
+                return Err(error(format!("{} needs at least one value", first.to_lowercase())));
+            }
+        }
+        statements.push(Statement { line: line_number, labels, kind });
+    }
+    Ok(statements)
+}
+
+//"0x200  6005      LD V0, 0x05" from disasm::disassemble. no statement starts with a number so the
+//columns can't be mistaken for anything else
+fn skip_listing_columns(line: &str) -> &str {
+    let Some((address, rest)) = line.split_once(char::is_whitespace) else {
+        return line;
+    };
+    let (bytes, rest) = rest.trim_start().split_once(char::is_whitespace).unwrap_or((rest.trim_start(), ""));
+    let is_hex = |text: &str| !text.is_empty() && text.chars().all(|digit| digit.is_ascii_hexdigit());
+    match address.strip_prefix("0x") {
+        Some(address) if is_hex(address) && is_hex(bytes) && bytes.len() % 2 == 0 => rest.trim(),
+        _ => line
+    }
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next().is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|rest| rest.is_ascii_alphanumeric() || rest == '_' || rest == '.')
+        && register(text).is_none()
+        && !["I", "DT", "ST", "K", "F", "HF", "B", "R", "LONG"].contains(&text.to_uppercase().as_str())
+}
+
+fn identifier(text: &str) -> Result<String, String> {
+    if is_identifier(text) {
+        Ok(text.to_string())
+    } else {
+        Err(format!("'{}' can't be used as a name", text))
+    }
+}
+
+fn define(symbols: &mut HashMap<String, i64>, name: &str, value: i64) -> Result<(), String> {
+    if symbols.insert(name.to_string(), value).is_some() {
+        return Err(format!("'{}' is defined twice", name));
+    }
+    Ok(())
+}
+
+fn org(expression: &str, symbols: &HashMap<String, i64>, address: usize) -> Result<usize, String> {
+    let target = evaluate(expression, symbols, address)?;
+    if !(0..=0xFFFF).contains(&target) {
+        return Err(format!("org 0x{:X} is outside memory", target));
+    }
+    Ok(target as usize)
+}
+
+//Vx
+fn register(operand: &str) -> Option<u8> {
+    let digit = operand.strip_prefix('V').or_else(|| operand.strip_prefix('v'))?;
+    if digit.len() != 1 {
+        return None;
+    }
+    u8::from_str_radix(digit, 16).ok()
+}
+
+//LD I, LONG expr, or LD I, expr when expr is already known to need 16 bits
+fn long_load(mnemonic: &str, operands: &[String], symbols: &HashMap<String, i64>, address: usize) -> bool {
+    match (mnemonic, operands) {
+        ("LD", [target, source]) if target.eq_ignore_ascii_case("I") => {
+            long_operand(source).is_some() || evaluate(source, symbols, address).is_ok_and(|value| value > 0xFFF)
+        }
+        _ => false
+    }
+}
+
+fn long_operand(operand: &str) -> Option<&str> {
+    let (keyword, rest) = operand.split_once(char::is_whitespace)?;
+    keyword.eq_ignore_ascii_case("LONG").then_some(rest.trim())
+}
+
+fn value_in(expression: &str, symbols: &HashMap<String, i64>, address: usize, min: i64, max: i64, what: &str) -> Result<u16, String> {
+    let value = evaluate(expression, symbols, address)?;
+    if value < min || value > max {
+        return Err(format!("{} {} is outside {}..={}", what, value, min, max));
+    }
+    Ok(value as u16)
+}
+
+//bytes accept -128..=255 so negative offsets can be written directly
+fn byte(expression: &str, symbols: &HashMap<String, i64>, address: usize) -> Result<u8, String> {
+    value_in(expression, symbols, address, -128, 0xFF, "value").map(|value| value as u8)
+}
+
+fn word(expression: &str, symbols: &HashMap<String, i64>, address: usize) -> Result<u16, String> {
+    value_in(expression, symbols, address, -0x8000, 0xFFFF, "value")
+}
+
+fn encode(mnemonic: &str, operands: &[String], symbols: &HashMap<String, i64>, address: usize) -> Result<Vec<u8>, String> {
+    use Instruction::*;
+    let value = |expression: &str, max: i64, what: &str| value_in(expression, symbols, address, 0, max, what);
+    let kk = |expression: &str| byte(expression, symbols, address);
+    let nnn = |expression: &str| value(expression, 0xFFF, "address");
+    let nibble = |expression: &str| value(expression, 0xF, "value").map(|value| value as u8);
+    let upper: Vec<String> = operands.iter().map(|operand| operand.to_uppercase()).collect();
+    let operand: Vec<&str> = upper.iter().map(String::as_str).collect();
+    let regs: Vec<Option<u8>> = operands.iter().map(|operand| register(operand)).collect();
+
+    let instruction = match (mnemonic, &operand[..], &regs[..]) {
+        ("CLS", [], _) => Cls,
+        ("RET", [], _) => Ret,
+        ("SCD", [_], _) => Scd(nibble(&operands[0])?),
+        ("SCU", [_], _) => Scu(nibble(&operands[0])?),
+        ("SCR", [], _) => Scr,
+        ("SCL", [], _) => Scl,
+        ("EXIT", [], _) => Exit,
+        ("LOW", [], _) => Low,
+        ("HIGH", [], _) => High,
+        ("SYS", [_], _) => Sys(nnn(&operands[0])?),
+        ("JP", ["V0", _], _) => JpV0(nnn(&operands[1])?),
+        ("JP", [_], _) => Jp(nnn(&operands[0])?),
+        ("CALL", [_], _) => Call(nnn(&operands[0])?),
+        ("SE", _, [Some(x), Some(y)]) => SeVxVy(*x, *y),
+        ("SE", _, [Some(x), None]) => SeVxByte(*x, kk(&operands[1])?),
+        ("SNE", _, [Some(x), Some(y)]) => SneVxVy(*x, *y),
+        ("SNE", _, [Some(x), None]) => SneVxByte(*x, kk(&operands[1])?),
+        ("SAVE", _, [Some(x), Some(y)]) => Save(*x, *y),
+        ("LOAD", _, [Some(x), Some(y)]) => Load(*x, *y),
+        ("LD", ["I", _], _) => {
+            let (target, long) = match long_operand(&operands[1]) {
+                Some(target) => (value(target, 0xFFFF, "address")?, true),
+                None => {
+                    let target = value(&operands[1], 0xFFFF, "address")?;
+                    (target, target > 0xFFF)
+                }
+            };
+            if long {
+                return Ok([LdILong.encode().to_be_bytes(), target.to_be_bytes()].concat());
+            }
+            LdI(target)
+        }
+        ("LD", ["DT", _], [_, Some(x)]) => LdDtVx(*x),
+        ("LD", ["ST", _], [_, Some(x)]) => LdStVx(*x),
+        ("LD", ["F", _], [_, Some(x)]) => LdFVx(*x),
+        ("LD", ["HF", _], [_, Some(x)]) => LdHfVx(*x),
+        ("LD", ["B", _], [_, Some(x)]) => LdBVx(*x),
+        ("LD", ["[I]", _], [_, Some(x)]) => LdIVx(*x),
+        ("LD", ["R", _], [_, Some(x)]) => LdRVx(*x),
+        ("LD", [_, "DT"], [Some(x), _]) => LdVxDt(*x),
+        ("LD", [_, "K"], [Some(x), _]) => LdVxK(*x),
+        ("LD", [_, "[I]"], [Some(x), _]) => LdVxI(*x),
+        ("LD", [_, "R"], [Some(x), _]) => LdVxR(*x),
+        ("LD", _, [Some(x), Some(y)]) => LdVxVy(*x, *y),
+        ("LD", _, [Some(x), None]) => LdVxByte(*x, kk(&operands[1])?),
+        ("ADD", ["I", _], [_, Some(x)]) => AddIVx(*x),
+        ("ADD", _, [Some(x), Some(y)]) => AddVxVy(*x, *y),
+        ("ADD", _, [Some(x), None]) => AddVxByte(*x, kk(&operands[1])?),
+        ("OR", _, [Some(x), Some(y)]) => Or(*x, *y),
+        ("AND", _, [Some(x), Some(y)]) => And(*x, *y),
+        ("XOR", _, [Some(x), Some(y)]) => Xor(*x, *y),
+        ("SUB", _, [Some(x), Some(y)]) => Sub(*x, *y),
+        ("SHR", _, [Some(x)]) => Shr(*x, *x),
+        ("SHR", _, [Some(x), Some(y)]) => Shr(*x, *y),
+        ("SUBN", _, [Some(x), Some(y)]) => Subn(*x, *y),
+        ("SHL", _, [Some(x)]) => Shl(*x, *x),
+        ("SHL", _, [Some(x), Some(y)]) => Shl(*x, *y),
+        ("RND", _, [Some(x), None]) => Rnd(*x, kk(&operands[1])?),
+        ("DRW", _, [Some(x), Some(y), None]) => Drw { x: *x, y: *y, n: nibble(&operands[2])? },
+        ("SKP", _, [Some(x)]) => Skp(*x),
+        ("SKNP", _, [Some(x)]) => Sknp(*x),
+        ("PLANE", [_], _) => Plane(nibble(&operands[0])?),
+        ("AUDIO", [], _) => Audio,
+        ("PITCH", _, [Some(x)]) => Pitch(*x),
+        ("CLS" | "RET" | "SCD" | "SCU" | "SCR" | "SCL" | "EXIT" | "LOW" | "HIGH" | "SYS" | "JP" | "CALL" | "SE" | "SNE" | "SAVE" | "LOAD"
+            | "LD" | "ADD" | "OR" | "AND" | "XOR" | "SUB" | "SHR" | "SUBN" | "SHL" | "RND" | "DRW" | "SKP" | "SKNP" | "PLANE"
+            | "AUDIO" | "PITCH", ..) => return Err(format!("invalid operands for {}: '{}'", mnemonic, operands.join(", "))),
+        _ => return Err(format!("unknown instruction '{}'", mnemonic))
+    };
+    Ok(instruction.encode().to_be_bytes().to_vec())
+}
+
+//expressions: numbers (42, 0x2A, 0b101010), names, $, parentheses, unary - and ~,
+//and * / % + - << >> & ^ | with C precedence
+fn evaluate(expression: &str, symbols: &HashMap<String, i64>, address: usize) -> Result<i64, String> {
+    let tokens = tokenize(expression)?;
+    if tokens.is_empty() {
+        return Err("missing value".to_string());
+    }
+    let mut parser = Parser { tokens, position: 0, symbols, address };
+    let value = parser.binary(0)?;
+    match parser.tokens.get(parser.position) {
+        None => Ok(value),
+        Some(token) => Err(format!("unexpected '{}' in '{}'", token, expression.trim()))
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<String>, String> {
+    let chars: Vec<char> = expression.chars().collect();
+    let mut tokens = Vec::new();
+    let mut idx = 0;
+    while idx < chars.len() {
+        let current = chars[idx];
+        if current.is_whitespace() {
+            idx += 1;
+        } else if current.is_ascii_alphanumeric() || current == '_' || current == '.' {
+            let start = idx;
+            while idx < chars.len() && (chars[idx].is_ascii_alphanumeric() || chars[idx] == '_' || chars[idx] == '.') {
+                idx += 1;
+            }
+            tokens.push(chars[start..idx].iter().collect());
+        } else if (current == '<' || current == '>') && chars.get(idx + 1) == Some(&current) {
+            tokens.push(format!("{}{}", current, current));
+            idx += 2;
+        } else if "+-*/%&|^~()$".contains(current) {
+            tokens.push(current.to_string());
+            idx += 1;
+        } else {
+            return Err(format!("unexpected '{}' in '{}'", current, expression.trim()));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<String>,
+    position: usize,
+    symbols: &'a HashMap<String, i64>,
+    address: usize,
+}
+
+//expressions are worked out in 64 bits, far more than any operand holds, but still can't wrap
+const OVERFLOW: &str = "value too large for an expression";
+
+//binding power of each binary operator, higher binds tighter
+const BINARY: [(&str, u8); 10] = [
+    ("|", 1), ("^", 2), ("&", 3), ("<<", 4), (">>", 4), ("+", 5), ("-", 5), ("*", 6), ("/", 6), ("%", 6)
+];
+
+impl Parser<'_> {
+    fn binary(&mut self, min_power: u8) -> Result<i64, String> {
+        let mut left = self.unary()?;
+        while let Some(&(operator, power)) = self.tokens.get(self.position)
+            .and_then(|token| BINARY.iter().find(|(operator, _)| *operator == token.as_str())) {
+            if power <= min_power {
+                break;
+            }
+            self.position += 1;
+            let right = self.binary(power)?;
+            left = match operator {
+                "|" => left | right,
+                "^" => left ^ right,
+                "&" => left & right,
+                "<<" => left.checked_shl(right as u32).ok_or("shift out of range")?,
+                ">>" => left.checked_shr(right as u32).ok_or("shift out of range")?,
+                "+" => left.checked_add(right).ok_or(OVERFLOW)?,
+                "-" => left.checked_sub(right).ok_or(OVERFLOW)?,
+                "*" => left.checked_mul(right).ok_or(OVERFLOW)?,
+                "/" | "%" if right == 0 => return Err("division by zero".to_string()),
+                "/" => left.checked_div(right).ok_or(OVERFLOW)?,
+                _ => left.checked_rem(right).ok_or(OVERFLOW)?,
+            };
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<i64, String> {
+        let token = self.tokens.get(self.position).cloned().ok_or("expression ends early")?;
+        self.position += 1;
+        match token.as_str() {
+            "-" => self.unary()?.checked_neg().ok_or(OVERFLOW.to_string()),
+            "~" => Ok(!self.unary()?),
+            "$" => Ok(self.address as i64),
+            "(" => {
+                let value = self.binary(0)?;
+                if self.tokens.get(self.position).map(String::as_str) != Some(")") {
+                    return Err("missing ')'".to_string());
+                }
+                self.position += 1;
+                Ok(value)
+            }
+            number if number.starts_with(|first: char| first.is_ascii_digit()) => {
+                let lower = number.to_lowercase();
+                let parsed = if let Some(hex) = lower.strip_prefix("0x") {
+                    i64::from_str_radix(hex, 16)
+                } else if let Some(binary) = lower.strip_prefix("0b") {
+                    i64::from_str_radix(binary, 2)
+                } else {
+                    lower.parse()
+                };
+                parsed.map_err(|_| format!("invalid number '{}'", number))
+            }
+            name => self.symbols.get(name).copied().ok_or(format!("unknown name '{}'", name))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chip8::Platform;
+    use crate::disasm::{disassemble, Syntax};
+
+    fn error(source: &str) -> AsmError {
+        assemble(source).unwrap_err()
+    }
+
+    #[test]
+    fn overflowing_expressions_are_errors() {
+        for expression in ["0x7FFFFFFFFFFFFFFF + 1", "0x7FFFFFFFFFFFFFFF * 3", "-0x7FFFFFFFFFFFFFFF - 2",
+            "-(-0x7FFFFFFFFFFFFFFF - 1)", "(-0x7FFFFFFFFFFFFFFF - 1) / -1", "(-0x7FFFFFFFFFFFFFFF - 1) % -1"] {
+            let source = format!("CLS\nDB {}", expression);
+            assert_eq!(error(&source), AsmError { line: 2, message: OVERFLOW.to_string() }, "{}", expression);
+        }
+        assert_eq!(error("DB 1 / 0").message, "division by zero");
+        assert_eq!(error("DB 1 % (2 - 2)").message, "division by zero");
+    }
+
+    #[test]
+    fn instructions_and_data() {
+        assert_eq!(assemble("CLS\nLD V3, 0x1F\nDRW VA, vB, 15\nLD [I], V2\nSHR V4").unwrap(),
+            [0x00, 0xE0, 0x63, 0x1F, 0xDA, 0xBF, 0xF2, 0x55, 0x84, 0x46]);
+        assert_eq!(assemble("db 1, -1, 0xFF\ndw 0x1234, -2").unwrap(), [1, 0xFF, 0xFF, 0x12, 0x34, 0xFF, 0xFE]);
+        assert_eq!(assemble("; nothing but a comment\n\n   ").unwrap(), []);
+    }
+
+    #[test]
+    fn labels_and_constants() {
+        let rom = assemble("
+            start: again: JP end     ; labels can share a line and be used before they're defined
+            SPEED = 3
+            DOUBLE equ SPEED * 2
+            LD V0, DOUBLE
+            LD V1, end - start
+            JP again
+            end: JP $
+        ").unwrap();
+        assert_eq!(rom, [0x12, 0x08, 0x60, 0x06, 0x61, 0x08, 0x12, 0x00, 0x12, 0x08]);
+        assert_eq!(assemble("org 0x300\nhere: JP here\norg 0x310\nDB 1").unwrap().len(), 0x11, "gaps are zero filled");
+    }
+
+    #[test]
+    fn expressions() {
+        let value = |expression: &str| assemble(&format!("DW {}", expression)).map(|rom| u16::from_be_bytes([rom[0], rom[1]]));
+        assert_eq!(value("1 + 2 * 3"), Ok(7));
+        assert_eq!(value("(1 + 2) * 3"), Ok(9));
+        assert_eq!(value("1 << 4 | 1"), Ok(0x11));
+        assert_eq!(value("0xF0 & ~0x30 ^ 1"), Ok(0xC1));
+        assert_eq!(value("0b1010 >> 1 - 1"), Ok(10), "- binds tighter than >>");
+        assert_eq!(value("17 % 5 / 2 - -3"), Ok(4));
+        assert_eq!(value("$ + 2"), Ok(0x202));
+        assert_eq!(value("10 - 4 - 3"), Ok(3), "operators group to the left");
+    }
+
+    #[test]
+    fn long_loads_are_sized_in_the_first_pass() {
+        assert_eq!(assemble("LD I, 0xFFF").unwrap(), [0xAF, 0xFF]);
+        assert_eq!(assemble("LD I, 0x1000").unwrap(), [0xF0, 0x00, 0x10, 0x00]);
+        assert_eq!(assemble("LD I, LONG 0x300").unwrap(), [0xF0, 0x00, 0x03, 0x00]);
+        //a constant or a label above 0xFFF that is known by then is enough
+        let rom = assemble("TOP = 0x2000\nLD I, TOP\nJP next\nnext: CLS").unwrap();
+        assert_eq!(rom, [0xF0, 0x00, 0x20, 0x00, 0x12, 0x06, 0x00, 0xE0]);
+        let rom = assemble("org 0x1000\ndata: DB 7\norg 0x200\nLD I, data").unwrap_err();
+        assert_eq!(rom.message, "org 0x200 is below the start of the program at 0x1000");
+        assert_eq!(error("LD I, far\norg 0x1000\nfar: DB 0"),
+            AsmError { line: 1, message: "LD I to a label above 0xFFF defined later in the file needs LD I, LONG".to_string() });
+        assert_eq!(assemble("LD I, LONG far\norg 0x1000\nfar: DB 0").unwrap()[..4], [0xF0, 0x00, 0x10, 0x00]);
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let cases = [
+            ("CLS\nFOO V0", 2, "unknown instruction 'FOO'"),
+            ("LD V0, V1, V2", 1, "invalid operands for LD: 'V0, V1, V2'"),
+            ("a: CLS\na: CLS", 2, "'a' is defined twice"),
+            ("JP nowhere", 1, "unknown name 'nowhere'"),
+            ("LD V0, 256", 1, "value 256 is outside -128..=255"),
+            ("JP 0x1000", 1, "address 4096 is outside 0..=4095"),
+            ("DRW V0, V1, 16", 1, "value 16 is outside 0..=15"),
+            ("DB", 1, "db needs at least one value"),
+            ("V0 = 1", 1, "'V0' can't be used as a name"),
+            ("DB (1 + 2", 1, "missing ')'"),
+            ("DB 1 +", 1, "expression ends early"),
+            ("DB 1 2", 1, "unexpected '2' in '1 2'"),
+            ("DB 0x", 1, "invalid number '0x'"),
+            ("DB 1 # 2", 1, "unexpected '#' in '1 # 2'"),
+            ("org 0x10000", 1, "org 0x10000 is outside memory"),
+            ("org 0xFFFF\nDW 0", 2, "program runs past the end of memory at 0xFFFF"),
+        ];
+        for (source, line, message) in cases {
+            assert_eq!(error(source), AsmError { line, message: message.to_string() }, "{}", source);
+        }
+        assert_eq!(error("JP nowhere").to_string(), "line 1: unknown name 'nowhere'");
+    }
+
+    #[test]
+    fn disassembly_assembles_back() {
+        let sources = [
+            include_str!("../tests/roms/flags.asm"), include_str!("../tests/roms/opcodes.asm"),
+            include_str!("../tests/roms/schip.asm"), include_str!("../tests/roms/xochip.asm"),
+        ];
+        for source in sources {
+            let rom = assemble(source).unwrap();
+            let listing = disassemble(&rom, 0x200, Platform::XoChip, Syntax::Cowgod);
+            assert_eq!(assemble(&listing).unwrap(), rom, "{}", listing);
+        }
+        //data nothing reaches, a long load and a ROM loaded somewhere other than 0x200
+        let rom = [0xF0, 0x00, 0x03, 0x0A, 0x22, 0x08, 0x13, 0x06, 0xD0, 0x11, 0x00, 0xEE, 0xAA, 0x55, 0x01];
+        let listing = disassemble(&rom, 0x300, Platform::XoChip, Syntax::Cowgod);
+        assert!(listing.starts_with("org 0x300\nmain:\n0x300  F000030A  LD I, LONG data_30A\n"), "{}", listing);
+        assert_eq!(assemble(&listing).unwrap(), rom, "{}", listing);
+    }
+
+    #[test]
+    fn listing_columns() {
+        assert_eq!(skip_listing_columns("0x200  6005      LD V0, 0x05"), "LD V0, 0x05");
+        assert_eq!(skip_listing_columns("0x20E  F09012  DB 0xF0, 0x90, 0x12"), "DB 0xF0, 0x90, 0x12");
+        assert_eq!(skip_listing_columns("0x200  600"), "0x200  600", "odd byte columns aren't a listing");
+        assert_eq!(skip_listing_columns("LD V0, 0x05"), "LD V0, 0x05");
+        assert_eq!(skip_listing_columns("org 0x300"), "org 0x300");
+    }
+}
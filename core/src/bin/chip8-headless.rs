@@ -0,0 +1,207 @@
+//Runs a ROM without a window for a fixed number of frames or cycles and dumps the final screen.
+//Used for regression testing on machines without a display.
+
+use std::{env, fs, process};
+use std::str::FromStr;
+use chip8rs_core::{dump, sha1_hex, Movie, Platform, Processor, Quirks, Rng, RngMode, FONTSET};
+use chip8rs_core::headless::{self, Hooks, Limit};
+
+const USAGE: &str = "\
+Usage: chip8-headless [OPTIONS] ROM
+
+Options:
+  --frames <N>          run N 60hz frames [default: 60, or the whole movie with --play]
+  --cycles <N>          run N instructions instead of a frame count
+  --ips <N>             instructions per second [default: 1000]
+  --platform <NAME>     chip8, schip or xochip [default: xochip]
+  --quirks <PRESET>     vip, chip48, schip or modern [default: the platform's preset]
+  --seed <N>            seed for Cxkk random numbers [default: 0]
+  --rng <NAME>          splitmix or vip [default: splitmix]
+  --load-state <FILE>   start from a save state written by chip8rs
+  --play <FILE>         replay a movie recorded with chip8rs --record, exits with 1 if it desyncs
+  --input <FILE>        key script, one 'FRAME KEY down|up' per line
+  --press <F:K[:N]>     hold key K (hex) from frame F for N frames [default: 1]
+  --output <FORMAT>     ascii, pbm or hash [default: ascii]
+  --out <FILE>          write the dump to FILE instead of stdout
+  -h, --help            print this message";
+
+//(frame, key, pressed)
+type KeyEvent = (u64, usize, bool);
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let mut rom = None;
+    let mut limit = None;
+    let mut instructions_per_second: u32 = 1000;
+    let mut platform = Platform::XoChip;
+    let mut quirks = None;
+    let mut seed = 0;
+    let mut rng = RngMode::SplitMix;
+    let mut state_file = None;
+    let mut movie_file = None;
+    let mut key_events: Vec<KeyEvent> = Vec::new();
+    let mut output = String::from("ascii");
+    let mut out_file = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            "--frames" => limit = Some(Limit::Frames(value(&arg, &mut args))),
+            "--cycles" => limit = Some(Limit::Cycles(value(&arg, &mut args))),
+            "--ips" => instructions_per_second = value(&arg, &mut args),
+            "--platform" => {
+                let name: String = value(&arg, &mut args);
+                platform = Platform::from_name(&name).unwrap_or_else(|| fail(&format!("unknown platform '{}'", name)));
+            }
+            "--quirks" => {
+                let name: String = value(&arg, &mut args);
+                quirks = Some(Quirks::from_preset(&name).unwrap_or_else(|| fail(&format!("unknown quirks preset '{}'", name))));
+            }
+            "--seed" => seed = value(&arg, &mut args),
+            "--rng" => {
+                let name: String = value(&arg, &mut args);
+                rng = RngMode::from_name(&name).unwrap_or_else(|| fail(&format!("unknown random number generator '{}'", name)));
+            }
+            "--load-state" => state_file = Some(value::<String, _>(&arg, &mut args)),
+            "--play" => movie_file = Some(value::<String, _>(&arg, &mut args)),
+            "--input" => {
+                let path: String = value(&arg, &mut args);
+                let script = fs::read_to_string(&path).unwrap_or_else(|error| fail(&format!("could not read {}: {}", path, error)));
+                key_events.extend(parse_script(&script).unwrap_or_else(|error| fail(&format!("{}: {}", path, error))));
+            }
+            "--press" => {
+                let spec: String = value(&arg, &mut args);
+                key_events.extend(parse_press(&spec).unwrap_or_else(|| fail(&format!("invalid --press '{}'", spec))));
+            }
+            "--output" => output = value(&arg, &mut args),
+            "--out" => out_file = Some(value::<String, _>(&arg, &mut args)),
+            flag if flag.starts_with('-') => fail(&format!("unknown option '{}'", flag)),
+            path => rom = Some(path.to_string()),
+        }
+    }
+    let rom = rom.unwrap_or_else(|| fail("no ROM given"));
+    if !["ascii", "pbm", "hash"].contains(&output.as_str()) {
+        fail(&format!("unknown output format '{}'", output));
+    }
+
+    let rom_data = fs::read(&rom).unwrap_or_else(|error| fail(&format!("could not read {}: {}", rom, error)));
+    let mut chip = Processor::new(FONTSET, quirks.unwrap_or(platform.default_quirks()), platform);
+    chip.load_rom(&rom_data).unwrap_or_else(|error| fail(&format!("{}: {}", rom, error)));
+    chip.set_rng(Rng::new(rng, seed));
+    if let Some(path) = state_file {
+        let state = fs::read(&path).unwrap_or_else(|error| fail(&format!("could not read {}: {}", path, error)));
+        chip.load_state(&state).unwrap_or_else(|error| fail(&format!("{}: {}", path, error)));
+    }
+    key_events.sort_by_key(|event| event.0);
+
+    let mut instructions_per_frame = (instructions_per_second / 60).max(1);
+    //a movie brings its own start state, speed and keys
+    let movie = movie_file.map(|path| {
+        let data = fs::read(&path).unwrap_or_else(|error| fail(&format!("could not read {}: {}", path, error)));
+        let movie = Movie::from_bytes(&data).unwrap_or_else(|error| fail(&format!("{}: {}", path, error)));
+        movie.start(&mut chip, &sha1_hex(&rom_data)).unwrap_or_else(|error| fail(&format!("{}: {}", path, error)));
+        instructions_per_frame = movie.get_instructions_per_frame();
+        movie
+    });
+    //the final state is only comparable after exactly the recorded frames
+    let verify = movie.is_some() && limit.is_none();
+    let limit = limit.unwrap_or(Limit::Frames(movie.as_ref().map_or(60, |movie| movie.len() as u64)));
+    let result = headless::run(&mut chip, instructions_per_frame, limit, &mut Keys { movie: movie.as_ref(), key_events: &key_events });
+
+    let screen = chip.get_vmemory();
+    let dumped = match output.as_str() {
+        "pbm" => dump::pbm(screen),
+        "hash" => format!("{:016x}\n", dump::hash(screen)),
+        _ => dump::ascii(screen),
+    };
+    match out_file {
+        Some(path) => fs::write(&path, dumped).unwrap_or_else(|error| fail(&format!("could not write {}: {}", path, error))),
+        None => print!("{}", dumped),
+    }
+
+    if let Err(error) = result {
+        eprintln!("Emulator crashed: {}", error);
+        process::exit(1);
+    }
+    if verify && movie.is_some_and(|movie| movie.verify(&chip) == Some(false)) {
+        eprintln!("Movie desynced: the final state differs from the recording");
+        process::exit(1);
+    }
+}
+
+
+//a movie's keys, or else the --input and --press ones
+struct Keys<'a> {
+    movie: Option<&'a Movie>,
+    //sorted by frame, the ones applied are cut off the front so each is only looked at once
+    key_events: &'a [KeyEvent],
+}
+
+impl Hooks for Keys<'_> {
+    fn before_frame(&mut self, chip: &mut Processor, frame: u64) -> bool {
+        if let Some(movie) = self.movie {
+            movie.apply(chip, frame);
+            return true;
+        }
+        let due = self.key_events.partition_point(|event| event.0 <= frame);
+        for &(_, key, pressed) in &self.key_events[..due] {
+            chip.set_key(key, pressed);
+        }
+        self.key_events = &self.key_events[due..];
+        true
+    }
+}
+
+fn parse_script(script: &str) -> Result<Vec<KeyEvent>, String> {
+    let mut events = Vec::new();
+    for (number, line) in script.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let event = match parts[..] {
+            [frame, key, state] => {
+                let frame = frame.parse().ok();
+                let key = parse_key(key);
+                let pressed = match state {
+                    "down" => Some(true),
+                    "up" => Some(false),
+                    _ => None
+                };
+                frame.zip(key).zip(pressed).map(|((frame, key), pressed)| (frame, key, pressed))
+            }
+            _ => None
+        };
+        events.push(event.ok_or(format!("line {}: expected 'FRAME KEY down|up'", number + 1))?);
+    }
+    Ok(events)
+}
+
+//F:K[:N] -> key down at frame F and up N frames later
+fn parse_press(spec: &str) -> Option<Vec<KeyEvent>> {
+    let parts: Vec<&str> = spec.split(':').collect();
+    let (frame, key, length) = match parts[..] {
+        [frame, key] => (frame.parse().ok()?, parse_key(key)?, 1),
+        [frame, key, length] => (frame.parse().ok()?, parse_key(key)?, length.parse().ok()?),
+        _ => return None
+    };
+    Some(vec![(frame, key, true), (frame + length, key, false)])
+}
+
+fn parse_key(key: &str) -> Option<usize> {
+    usize::from_str_radix(key.trim_start_matches("0x"), 16).ok().filter(|key| *key < 16)
+}
+
+fn value<T: FromStr, I: Iterator<Item = String>>(flag: &str, args: &mut I) -> T {
+    let raw = args.next().unwrap_or_else(|| fail(&format!("{} needs a value", flag)));
+    raw.parse().unwrap_or_else(|_| fail(&format!("invalid value '{}' for {}", raw, flag)))
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("error: {}\n\n{}", message, USAGE);
+    process::exit(2);
+}
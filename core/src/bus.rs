@@ -0,0 +1,235 @@
+//RAM as seen by the processor. Every instruction fetch and every load/store the ROM makes goes
+//through read/write/fetch so watchpoints can see it; ROM loading, fonts and save states use the
+//unwatched memory()/memory_mut() views.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    Execute,
+}
+
+//fires on the chosen kinds of access to start..=end
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub start: u16,
+    pub end: u16,
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+    //false only logs the access, true also pauses the debugger
+    pub pause: bool,
+}
+
+impl Watchpoint {
+    fn matches(&self, address: usize, access: Access) -> bool {
+        let kind = match access {
+            Access::Read => self.read,
+            Access::Write => self.write,
+            Access::Execute => self.execute,
+        };
+        kind && (self.start as usize..=self.end as usize).contains(&address)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WatchHit {
+    //the instruction that made the access
+    pub pc: u16,
+    pub address: u16,
+    pub access: Access,
+    //byte read or written, the first opcode byte for executes
+    pub value: u8,
+    pub pause: bool,
+}
+
+pub struct Bus {
+    memory: Vec<u8>,
+    watchpoints: Vec<Watchpoint>,
+    hits: Vec<WatchHit>,
+}
+
+impl Bus {
+    pub fn new(size: usize) -> Bus {
+        Bus { memory: vec![0x0; size], watchpoints: Vec::new(), hits: Vec::new() }
+    }
+
+    pub fn size(&self) -> usize {
+        self.memory.len()
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    pub(crate) fn memory_mut(&mut self) -> &mut [u8] {
+        &mut self.memory
+    }
+
+    //an unwatched store for tools and tests patching RAM, false past the end of memory
+    pub fn poke(&mut self, address: usize, value: u8) -> bool {
+        self.memory.get_mut(address).map(|byte| *byte = value).is_some()
+    }
+
+    //swaps in restored RAM but keeps the watchpoints, they belong to the user not the machine
+    pub(crate) fn replace_memory(&mut self, memory: Vec<u8>) {
+        self.memory = memory;
+        self.hits.clear();
+    }
+
+    //callers bounds check with Processor::memory_range first
+    pub(crate) fn read(&mut self, pc: u16, address: usize) -> u8 {
+        let value = self.memory[address];
+        self.watch(pc, address, Access::Read, value);
+        value
+    }
+
+    pub(crate) fn write(&mut self, pc: u16, address: usize, value: u8) {
+        self.memory[address] = value;
+        self.watch(pc, address, Access::Write, value);
+    }
+
+    //the opcode at pc
+    pub fn fetch(&mut self, pc: u16) -> u16 {
+        let address = pc as usize;
+        let opcode = ((self.memory[address] as u16) << 8) | self.memory[address + 1] as u16;
+        if !self.watchpoints.is_empty() {
+            if let Some(hit_address) = [address, address + 1].into_iter()
+                .find(|address| self.watchpoints.iter().any(|watch| watch.matches(*address, Access::Execute))) {
+                self.watch(pc, hit_address, Access::Execute, self.memory[address]);
+            }
+        }
+        opcode
+    }
+
+    fn watch(&mut self, pc: u16, address: usize, access: Access, value: u8) {
+        for watch in self.watchpoints.iter().filter(|watch| watch.matches(address, access)) {
+            self.hits.push(WatchHit { pc, address: address as u16, access, value, pause: watch.pause });
+        }
+    }
+
+    //replaces any watchpoint already starting at the same address
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.remove_watchpoint(watchpoint.start);
+        self.watchpoints.push(watchpoint);
+        self.watchpoints.sort_by_key(|watch| watch.start);
+    }
+
+    //false if no watchpoint starts at the address
+    pub fn remove_watchpoint(&mut self, start: u16) -> bool {
+        let count = self.watchpoints.len();
+        self.watchpoints.retain(|watch| watch.start != start);
+        self.watchpoints.len() != count
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    //watchpoints hit since the last take_hits, oldest first
+    pub fn hits(&self) -> &[WatchHit] {
+        &self.hits
+    }
+
+    pub fn take_hits(&mut self) -> Vec<WatchHit> {
+        std::mem::take(&mut self.hits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::chip8::{Platform, Processor};
+    use crate::fontset::FONTSET;
+
+    //Fx55 at 0x206 stores V0-V1 at 0x300, Fx65 at 0x20A loads V0-V2 back and Dxyn at 0x20E draws the
+    //two byte sprite at 0x30A
+    const ROM: &str = "
+        LD V0, 0x11
+        LD V1, 0x22
+        LD I, 0x300
+        LD [I], V1
+        LD I, 0x300
+        LD V2, [I]
+        LD I, 0x30A
+        DRW V0, V0, 2
+    ";
+
+    fn watch(start: u16, end: u16, read: bool, write: bool) -> Watchpoint {
+        Watchpoint { start, end, read, write, execute: false, pause: false }
+    }
+
+    fn run(watchpoints: &[Watchpoint]) -> Vec<WatchHit> {
+        let mut chip = Processor::new(FONTSET, Platform::Chip8.default_quirks(), Platform::Chip8);
+        chip.load_rom(&assemble(ROM).unwrap()).unwrap();
+        for watchpoint in watchpoints {
+            chip.get_bus_mut().add_watchpoint(*watchpoint);
+        }
+        for _ in 0..8 {
+            chip.cycle().unwrap();
+        }
+        chip.get_bus_mut().take_hits()
+    }
+
+    #[test]
+    fn stores_and_loads_hit_their_watchpoints() {
+        let hits = run(&[watch(0x301, 0x302, false, true)]);
+        assert_eq!(hits, [WatchHit { pc: 0x206, address: 0x301, access: Access::Write, value: 0x22, pause: false }],
+            "Fx55 only writes V0-V1, 0x302 is untouched");
+
+        let hits = run(&[watch(0x300, 0x302, true, false)]);
+        let reads: Vec<(u16, u16)> = hits.iter().map(|hit| (hit.pc, hit.address)).collect();
+        assert_eq!(reads, [(0x20A, 0x300), (0x20A, 0x301), (0x20A, 0x302)]);
+        assert!(hits.iter().all(|hit| hit.access == Access::Read));
+    }
+
+    #[test]
+    fn sprite_reads_hit_read_watchpoints() {
+        let hits = run(&[watch(0x30B, 0x30B, true, true)]);
+        assert_eq!(hits, [WatchHit { pc: 0x20E, address: 0x30B, access: Access::Read, value: 0, pause: false }]);
+    }
+
+    #[test]
+    fn fetches_hit_execute_watchpoints() {
+        let execute = |start, end| Watchpoint { start, end, read: false, write: false, execute: true, pause: true };
+        let hits = run(&[execute(0x206, 0x206)]);
+        assert_eq!(hits, [WatchHit { pc: 0x206, address: 0x206, access: Access::Execute, value: 0xF1, pause: true }]);
+        assert_eq!(run(&[execute(0x207, 0x207)]), [WatchHit { pc: 0x206, address: 0x207, access: Access::Execute, value: 0xF1, pause: true }],
+            "the second opcode byte fires too but reports the first");
+        assert_eq!(run(&[execute(0x300, 0x30B)]), [], "data accesses aren't executes");
+    }
+
+    #[test]
+    fn poke_is_unwatched_and_bounds_checked() {
+        let mut bus = Bus::new(0x1000);
+        bus.add_watchpoint(watch(0x1FF, 0x1FF, true, true));
+        assert!(bus.poke(0x1FF, 3));
+        assert!(!bus.poke(0x1000, 3));
+        assert_eq!((bus.memory()[0x1FF], bus.hits()), (3, &[][..]));
+    }
+
+    #[test]
+    fn other_addresses_and_kinds_dont_fire() {
+        assert_eq!(run(&[watch(0x303, 0x309, true, true), watch(0x30C, 0x400, true, true)]), []);
+        assert_eq!(run(&[watch(0x300, 0x30B, false, false)]), [], "a watchpoint with no kinds of access");
+        assert_eq!(run(&[]), []);
+    }
+
+    #[test]
+    fn watchpoints_are_keyed_by_start() {
+        let mut bus = Bus::new(0x1000);
+        bus.add_watchpoint(watch(0x300, 0x30F, true, false));
+        bus.add_watchpoint(watch(0x200, 0x2FF, false, true));
+        bus.add_watchpoint(watch(0x300, 0x300, false, true));
+        assert_eq!(bus.watchpoints(), [watch(0x200, 0x2FF, false, true), watch(0x300, 0x300, false, true)]);
+        assert!(bus.remove_watchpoint(0x200));
+        assert!(!bus.remove_watchpoint(0x200));
+
+        bus.write(0x200, 0x300, 7);
+        assert_eq!(bus.read(0x202, 0x300), 7);
+        assert_eq!(bus.hits().len(), 1);
+        bus.take_hits();
+        assert!(bus.hits().is_empty());
+    }
+}
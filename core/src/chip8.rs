@@ -0,0 +1,744 @@
+use std::ops::Range;
+
+
+use crate::bus::Bus;
+use crate::error::Chip8Error;
+use crate::fontset::BIG_FONTSET;
+use crate::framebuffer::Framebuffer;
+use crate::instruction::{decode, Instruction};
+use crate::quirks::Quirks;
+use crate::rng::Rng;
+use crate::state::{StateError, StateReader, StateWriter};
+use crate::trace::TraceEntry;
+
+const FONTSET_START_ADDRESS: usize = 0x50;
+//SCHIP hires digits are loaded straight after the small font
+const BIG_FONTSET_START_ADDRESS: usize = 0xA0;
+
+//which instruction set and memory map the ROM expects, each a superset of the one before
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Platform {
+    Chip8,
+    SuperChip,
+    XoChip,
+}
+
+impl Platform {
+    pub fn from_name(name: &str) -> Option<Platform> {
+        match name.to_lowercase().as_str() {
+            "chip8" | "chip-8" => Some(Platform::Chip8),
+            "schip" | "superchip" | "super-chip" => Some(Platform::SuperChip),
+            "xochip" | "xo-chip" => Some(Platform::XoChip),
+            _ => None
+        }
+    }
+
+    pub fn memory_size(&self) -> usize {
+        match self {
+            Platform::XoChip => 0x10000,
+            _ => 0x1000
+        }
+    }
+
+    //quirks of the interpreter ROMs for this platform were usually written against
+    pub fn default_quirks(&self) -> Quirks {
+        match self {
+            Platform::Chip8 => Quirks::vip(),
+            Platform::SuperChip => Quirks::schip(),
+            Platform::XoChip => Quirks::modern()
+        }
+    }
+}
+
+pub struct Processor {   
+    //registers
+    registers: [u8; 16],
+    index_register: u16,
+    program_counter: u16,
+    stack_pointer: u16,
+    //memory
+    vmemory: Framebuffer,
+    bus: Bus,
+    stack: [u16; 16],
+    keypad: [u8; 16],
+    //timers
+    sound_timer: u8,
+    delay_timer: u8,
+    //op
+    opcode: u16,
+    rom_start_address: usize,
+    quirks: Quirks,
+    platform: Platform,
+    //SCHIP
+    rpl_flags: [u8; 16],
+    exited: bool,
+    //XO-CHIP
+    planes: u8,
+    audio_pattern: Option<[u8; 16]>,
+    pitch: u8,
+    //set by DXYN when the display wait quirk is on, ends the current frame early
+    waiting_for_vblank: bool,
+    //Cxkk
+    rng: Rng,
+    //instructions executed and frames finished since the processor was created. not part of save
+    //states, loading one doesn't turn these back
+    cycles: u64,
+    frames: u64,
+    //Some while tracing, filled by cycle and drained by the frontend
+    trace: Option<Vec<TraceEntry>>,
+    //whether the last DXYN turned any pixel off, for frontends that hold back half redrawn frames.
+    //not part of save states either
+    last_draw_erased: bool,
+}
+
+impl Processor {
+    pub fn new(fontset: [u8; 80], quirks: Quirks, platform: Platform) -> Processor {
+        //init chip8 processor
+        let mut processor = Processor { registers: [0x0; 16], index_register: 0x0, program_counter: 0x200, 
+            stack_pointer: 0, vmemory: Framebuffer::new(), bus: Bus::new(platform.memory_size()), stack: [0x0; 16], 
+            keypad: [0x0; 16], sound_timer: 0, delay_timer: 0, opcode: 0x0, 
+            rom_start_address: 0x200, quirks, platform, rpl_flags: [0x0; 16], exited: false,
+            planes: 1, audio_pattern: None, pitch: 64, waiting_for_vblank: false, rng: Rng::default(), cycles: 0, frames: 0, trace: None,
+            last_draw_erased: false };
+        //load fontset
+        let memory = processor.bus.memory_mut();
+        memory[FONTSET_START_ADDRESS..FONTSET_START_ADDRESS + fontset.len()].copy_from_slice(&fontset);
+        memory[BIG_FONTSET_START_ADDRESS..BIG_FONTSET_START_ADDRESS + BIG_FONTSET.len()].copy_from_slice(&BIG_FONTSET);
+
+        processor
+    }
+    pub fn get_vmemory(&self) -> &Framebuffer {
+        &self.vmemory
+    } 
+    pub fn get_keypad(&self) -> [u8; 16] {
+        self.keypad
+    }
+    pub fn set_key(&mut self, key: usize, pressed: bool) {
+        self.keypad[key] = pressed as u8;
+    }
+    //(delay, sound)
+    pub fn get_timers(&self) -> (u8, u8) {
+        (self.delay_timer, self.sound_timer)
+    }
+    //true when the last sprite drawn turned pixels off, as when a game erases a sprite to move it
+    pub fn get_last_draw_erased(&self) -> bool {
+        self.last_draw_erased
+    }
+    //set once the ROM executes SCHIP 00FD
+    pub fn has_exited(&self) -> bool {
+        self.exited
+    }
+    //XO-CHIP 16 byte (128 sample) 1-bit audio pattern, None until the ROM loads one with F002
+    pub fn get_audio_pattern(&self) -> Option<[u8; 16]> {
+        self.audio_pattern
+    }
+    //samples per second the audio pattern should be played back at, set by XO-CHIP Fx3A
+    pub fn get_playback_rate(&self) -> f32 {
+        4000.0 * 2f32.powf((self.pitch as f32 - 64.0) / 48.0)
+    }
+    //V0-VF
+    pub fn get_registers(&self) -> [u8; 16] {
+        self.registers
+    }
+    pub fn get_index_register(&self) -> u16 {
+        self.index_register
+    }
+    pub fn get_program_counter(&self) -> u16 {
+        self.program_counter
+    }
+    pub fn get_stack_pointer(&self) -> u16 {
+        self.stack_pointer
+    }
+    //return addresses currently on the stack, oldest first
+    pub fn get_stack(&self) -> &[u16] {
+        &self.stack[..self.stack_pointer as usize]
+    }
+    pub fn get_memory(&self) -> &[u8] {
+        self.bus.memory()
+    }
+    //RAM plus its watchpoints
+    pub fn get_bus(&self) -> &Bus {
+        &self.bus
+    }
+    pub fn get_bus_mut(&mut self) -> &mut Bus {
+        &mut self.bus
+    }
+    //the word at address, 0 past the end of RAM
+    pub fn peek_word(&self, address: u16) -> u16 {
+        let address = address as usize;
+        match self.bus.memory().get(address..address + 2) {
+            Some(bytes) => ((bytes[0] as u16) << 8) | bytes[1] as u16,
+            None => 0
+        }
+    }
+    //the last opcode fetched by cycle
+    pub fn get_opcode(&self) -> u16 {
+        self.opcode
+    }
+    pub fn get_platform(&self) -> Platform {
+        self.platform
+    }
+    pub fn get_quirks(&self) -> Quirks {
+        self.quirks
+    }
+    pub fn get_rng(&self) -> Rng {
+        self.rng
+    }
+    //seeds Cxkk, the default is SplitMix64 seeded with 0
+    pub fn set_rng(&mut self, rng: Rng) {
+        self.rng = rng;
+    }
+    pub fn get_cycle_count(&self) -> u64 {
+        self.cycles
+    }
+    pub fn get_frame_count(&self) -> u64 {
+        self.frames
+    }
+
+    //turning tracing off drops anything not yet taken
+    pub fn set_tracing(&mut self, enabled: bool) {
+        self.trace = enabled.then(|| self.trace.take().unwrap_or_default());
+    }
+
+    //every instruction traced since the last call
+    pub fn take_trace(&mut self) -> Vec<TraceEntry> {
+        self.trace.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+    pub fn load_rom(&mut self, buffer: &[u8]) -> Result<(), Chip8Error> {
+        let max = self.bus.size() - self.rom_start_address;
+        if buffer.len() > max {
+            return Err(Chip8Error::RomTooLarge { size: buffer.len(), max });
+        }
+        self.bus.memory_mut()[self.rom_start_address..self.rom_start_address + buffer.len()].copy_from_slice(buffer);
+        Ok(())
+    }
+
+    //snapshot of the whole machine, restored with load_state
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut writer = StateWriter::new();
+        writer.u8(self.platform as u8);
+        writer.u8(self.quirks.to_bits());
+        writer.bytes(&self.registers);
+        writer.u16(self.index_register);
+        writer.u16(self.program_counter);
+        writer.u16(self.stack_pointer);
+        for address in self.stack {
+            writer.u16(address);
+        }
+        writer.u16(self.opcode);
+        writer.u32(self.bus.size() as u32);
+        writer.bytes(self.bus.memory());
+        writer.u16(self.vmemory.width() as u16);
+        writer.u16(self.vmemory.height() as u16);
+        writer.bytes(self.vmemory.pixels());
+        writer.u8(self.delay_timer);
+        writer.u8(self.sound_timer);
+        writer.bytes(&self.keypad);
+        writer.bytes(&self.rpl_flags);
+        writer.bool(self.exited);
+        writer.u8(self.planes);
+        writer.bool(self.audio_pattern.is_some());
+        writer.bytes(&self.audio_pattern.unwrap_or([0x0; 16]));
+        writer.u8(self.pitch);
+        writer.bool(self.waiting_for_vblank);
+        let (rng_mode, rng_state) = self.rng.to_parts();
+        writer.u8(rng_mode);
+        writer.u64(rng_state);
+        writer.finish()
+    }
+
+    //everything is validated before the processor is touched, so a bad state leaves it as it was
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        let (mut reader, version) = StateReader::new(data)?;
+        let platform = match reader.u8()? {
+            0 => Platform::Chip8,
+            1 => Platform::SuperChip,
+            2 => Platform::XoChip,
+            _ => return Err(StateError::Invalid("platform"))
+        };
+        let quirks = Quirks::from_bits(reader.u8()?);
+        let registers = reader.array()?;
+        let index_register = reader.u16()?;
+        let program_counter = reader.u16()?;
+        let stack_pointer = reader.u16()?;
+        let mut stack = [0x0; 16];
+        for address in stack.iter_mut() {
+            *address = reader.u16()?;
+        }
+        if stack_pointer as usize > stack.len() {
+            return Err(StateError::Invalid("stack pointer"));
+        }
+        let opcode = reader.u16()?;
+        let memory_size = reader.u32()? as usize;
+        if memory_size != platform.memory_size() {
+            return Err(StateError::Invalid("memory size"));
+        }
+        let memory = reader.bytes(memory_size)?.to_vec();
+        let (width, height) = (reader.u16()? as usize, reader.u16()? as usize);
+        let pixels = reader.bytes(width * height)?.to_vec();
+        let vmemory = Framebuffer::from_pixels(width, height, pixels).ok_or(StateError::Invalid("resolution"))?;
+        let delay_timer = reader.u8()?;
+        let sound_timer = reader.u8()?;
+        let keypad = reader.array()?;
+        let rpl_flags = reader.array()?;
+        let exited = reader.bool()?;
+        let planes = reader.u8()?;
+        let has_pattern = reader.bool()?;
+        let pattern = reader.array()?;
+        let pitch = reader.u8()?;
+        let waiting_for_vblank = reader.bool()?;
+        //version 1 states predate the seedable RNG and keep the current one
+        let rng = if version >= 2 {
+            let mode = reader.u8()?;
+            Rng::from_parts(mode, reader.u64()?).ok_or(StateError::Invalid("random number generator"))?
+        } else {
+            self.rng
+        };
+
+        let mut bus = std::mem::replace(&mut self.bus, Bus::new(0));
+        bus.replace_memory(memory);
+        *self = Processor { registers, index_register, program_counter, stack_pointer, vmemory, bus, stack,
+            keypad, sound_timer, delay_timer, opcode, rom_start_address: self.rom_start_address, quirks, platform,
+            rpl_flags, exited, planes, audio_pattern: has_pattern.then_some(pattern), pitch, waiting_for_vblank, rng,
+            cycles: self.cycles, frames: self.frames, trace: self.trace.take(), last_draw_erased: false };
+        Ok(())
+    }
+
+    //one 60hz frame: run up to `instructions_per_frame` instructions then tick both timers.
+    //the frontend presents the framebuffer after each call
+    pub fn run_frame(&mut self, instructions_per_frame: u32) -> Result<(), Chip8Error> {
+        self.run_frame_until(instructions_per_frame, |_| false).map(|_| ())
+    }
+
+    //run_frame that asks `stop` before every instruction. returns true if it stopped, in which case
+    //the frame is left unfinished and the timers aren't ticked
+    pub fn run_frame_until<F: FnMut(&Processor) -> bool>(&mut self, instructions_per_frame: u32, mut stop: F) -> Result<bool, Chip8Error> {
+        self.waiting_for_vblank = false;
+        for _ in 0..instructions_per_frame {
+            if self.exited || self.waiting_for_vblank {
+                break;
+            }
+            if stop(self) {
+                return Ok(true);
+            }
+            self.cycle()?;
+        }
+        self.tick_timers();
+        self.frames += 1;
+        Ok(false)
+    }
+
+    pub fn tick_timers(&mut self) {
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+    }
+
+    pub fn cycle(&mut self) -> Result<(), Chip8Error> {
+        let pc = self.program_counter;
+        if pc as usize + 1 >= self.bus.size() {
+            return Err(Chip8Error::PcOutOfBounds { pc });
+        }
+        self.opcode = self.bus.fetch(pc);
+        self.program_counter = pc.wrapping_add(2);
+        match decode(self.opcode) {
+            Some(instruction) => self.execute(instruction)?,
+            None => return Err(Chip8Error::UnknownOpcode { pc, opcode: self.opcode })
+        }
+        if self.trace.is_some() {
+            let next_word = self.peek_word(pc.wrapping_add(2));
+            if let Some(trace) = self.trace.as_mut() {
+                trace.push(TraceEntry { cycle: self.cycles, frame: self.frames, pc, opcode: self.opcode, next_word,
+                    registers: self.registers, index_register: self.index_register, stack_pointer: self.stack_pointer });
+            }
+        }
+        self.cycles += 1;
+        Ok(())
+    }
+
+    //XO-CHIP F000 nnnn is 4 bytes long so skipping it has to jump over both words
+    fn skip_next_instruction(&mut self) {
+        let pc = self.program_counter as usize;
+        if self.platform == Platform::XoChip && self.bus.memory().get(pc..pc + 2) == Some(&[0xF0, 0x00]) {
+            self.program_counter = self.program_counter.wrapping_add(4);
+        } else {
+            self.program_counter = self.program_counter.wrapping_add(2);
+        }
+    }
+
+    //memory[address..address + len] or the fault for the instruction at pc if that runs past the end of RAM
+    fn memory_range(&self, pc: u16, address: usize, len: usize) -> Result<Range<usize>, Chip8Error> {
+        if address + len > self.bus.size() {
+            return Err(Chip8Error::MemoryOutOfBounds { pc, opcode: self.opcode, address: address.max(self.bus.size()) });
+        }
+        Ok(address..address + len)
+    }
+
+    //runs an instruction cycle has already fetched, so program_counter points just past its opcode
+    pub fn execute(&mut self, instruction: Instruction) -> Result<(), Chip8Error> {
+        let pc = self.program_counter.wrapping_sub(2);
+        self.opcode = instruction.encode();
+        if !instruction.is_supported_on(self.platform) {
+            return Err(Chip8Error::UnknownOpcode { pc, opcode: self.opcode });
+        }
+        match instruction {
+            Instruction::Sys(_) => unreachable!("Sys is never supported"),
+            Instruction::Cls => self.vmemory.clear(self.planes),
+            Instruction::Ret => {
+                if self.stack_pointer == 0 {
+                    return Err(Chip8Error::StackUnderflow { pc, opcode: self.opcode });
+                }
+                self.stack_pointer -= 1;
+                self.program_counter = self.stack[self.stack_pointer as usize];
+            }
+            Instruction::Scd(n) => self.vmemory.scroll_down(n as usize, self.planes),
+            Instruction::Scu(n) => self.vmemory.scroll_up(n as usize, self.planes),
+            Instruction::Scr => self.vmemory.scroll_right(4, self.planes),
+            Instruction::Scl => self.vmemory.scroll_left(4, self.planes),
+            Instruction::Exit => {
+                //stays on 00FD so the frontend sees the same instruction every frame
+                self.exited = true;
+                self.program_counter = pc;
+            }
+            Instruction::Low => self.vmemory.set_hires(false),
+            Instruction::High => self.vmemory.set_hires(true),
+            Instruction::Jp(nnn) => self.program_counter = nnn,
+            Instruction::Call(nnn) => {
+                if self.stack_pointer as usize >= self.stack.len() {
+                    return Err(Chip8Error::StackOverflow { pc, opcode: self.opcode });
+                }
+                self.stack[self.stack_pointer as usize] = self.program_counter;
+                self.stack_pointer += 1;
+                self.program_counter = nnn;
+            }
+            Instruction::SeVxByte(x, kk) => {
+                if self.registers[x as usize] == kk {
+                    self.skip_next_instruction();
+                }
+            }
+            Instruction::SneVxByte(x, kk) => {
+                if self.registers[x as usize] != kk {
+                    self.skip_next_instruction();
+                }
+            }
+            Instruction::SeVxVy(x, y) => {
+                if self.registers[x as usize] == self.registers[y as usize] {
+                    self.skip_next_instruction();
+                }
+            }
+            Instruction::SneVxVy(x, y) => {
+                if self.registers[x as usize] != self.registers[y as usize] {
+                    self.skip_next_instruction();
+                }
+            }
+            Instruction::Save(x, y) => {
+                //store registers Vx through Vy in memory starting at Index, Vy..Vx when y < x
+                let range = self.memory_range(pc, self.index_register as usize, x.abs_diff(y) as usize + 1)?;
+                for (address, reg) in range.zip(register_range(x, y)) {
+                    self.bus.write(pc, address, self.registers[reg]);
+                }
+            }
+            Instruction::Load(x, y) => {
+                let range = self.memory_range(pc, self.index_register as usize, x.abs_diff(y) as usize + 1)?;
+                for (address, reg) in range.zip(register_range(x, y)) {
+                    self.registers[reg] = self.bus.read(pc, address);
+                }
+            }
+            Instruction::LdVxByte(x, kk) => self.registers[x as usize] = kk,
+            Instruction::AddVxByte(x, kk) => {
+                //no carry flag for 7xkk
+                self.registers[x as usize] = self.registers[x as usize].wrapping_add(kk);
+            }
+            Instruction::LdVxVy(x, y) => self.registers[x as usize] = self.registers[y as usize],
+            Instruction::Or(x, y) => {
+                self.registers[x as usize] |= self.registers[y as usize];
+                if self.quirks.logic_resets_vf {
+                    self.registers[0xF] = 0;
+                }
+            }
+            Instruction::And(x, y) => {
+                self.registers[x as usize] &= self.registers[y as usize];
+                if self.quirks.logic_resets_vf {
+                    self.registers[0xF] = 0;
+                }
+            }
+            Instruction::Xor(x, y) => {
+                self.registers[x as usize] ^= self.registers[y as usize];
+                if self.quirks.logic_resets_vf {
+                    self.registers[0xF] = 0;
+                }
+            }
+            //the flag is written after the result so it wins when x is F
+            Instruction::AddVxVy(x, y) => {
+                //VF = 1 if the sum is over 255 (8 bits)
+                let (rx, ry) = (self.registers[x as usize], self.registers[y as usize]);
+                let (sum, carry) = rx.overflowing_add(ry);
+                self.set_with_flag(x, sum, carry);
+            }
+            Instruction::Sub(x, y) => {
+                //VF = 1 if no borrow / x >= y
+                let (rx, ry) = (self.registers[x as usize], self.registers[y as usize]);
+                self.set_with_flag(x, rx.wrapping_sub(ry), rx >= ry);
+            }
+            Instruction::Shr(x, y) => {
+                //VF = least significant bit then divide by 2. VIP shifts Vy into Vx instead
+                let value = if self.quirks.shift_uses_vy { self.registers[y as usize] } else { self.registers[x as usize] };
+                self.set_with_flag(x, value >> 1, value & 0x1 != 0);
+            }
+            Instruction::Subn(x, y) => {
+                //Vx = Vy - Vx, VF = 1 if no borrow / y >= x
+                let (rx, ry) = (self.registers[x as usize], self.registers[y as usize]);
+                self.set_with_flag(x, ry.wrapping_sub(rx), ry >= rx);
+            }
+            Instruction::Shl(x, y) => {
+                //VF = most significant bit then multiply by 2
+                let value = if self.quirks.shift_uses_vy { self.registers[y as usize] } else { self.registers[x as usize] };
+                self.set_with_flag(x, value << 1, value & 0x80 != 0);
+            }
+            Instruction::LdI(nnn) => self.index_register = nnn,
+            Instruction::JpV0(nnn) => {
+                //CHIP-48/SCHIP read this as Bxnn and jump to xnn + Vx
+                let offset = if self.quirks.jump_uses_vx { self.registers[(nnn >> 8) as usize] } else { self.registers[0] };
+                self.program_counter = nnn + offset as u16;
+            }
+            Instruction::Rnd(x, kk) => {
+                let random_byte = self.rng.next_byte();
+                self.registers[x as usize] = random_byte & kk;
+            }
+            Instruction::Drw { x, y, n } => self.draw(pc, x, y, n)?,
+            Instruction::Skp(x) => {
+                if self.keypad[self.registers[x as usize] as usize & 0xF] == 1 {
+                    self.skip_next_instruction();
+                }
+            }
+            Instruction::Sknp(x) => {
+                if self.keypad[self.registers[x as usize] as usize & 0xF] == 0 {
+                    self.skip_next_instruction();
+                }
+            }
+            Instruction::LdILong => {
+                //index = the 16 bit address in the next word
+                let range = self.memory_range(pc, self.program_counter as usize, 2)?;
+                self.index_register = ((self.bus.read(pc, range.start) as u16) << 8) | self.bus.read(pc, range.start + 1) as u16;
+                self.program_counter = self.program_counter.wrapping_add(2);
+            }
+            Instruction::Plane(n) => {
+                //bitplanes used by drawing, clearing and scrolling
+                self.planes = n & 0x3;
+            }
+            Instruction::Audio => {
+                //16 byte audio pattern from memory starting at Index
+                let range = self.memory_range(pc, self.index_register as usize, 16)?;
+                let mut pattern = [0x0; 16];
+                for (byte, address) in pattern.iter_mut().zip(range) {
+                    *byte = self.bus.read(pc, address);
+                }
+                self.audio_pattern = Some(pattern);
+            }
+            Instruction::LdVxDt(x) => self.registers[x as usize] = self.delay_timer,
+            Instruction::LdVxK(x) => {
+                //wait for a key press by running this instruction again until one is down
+                match self.keypad.iter().position(|key| *key == 1) {
+                    Some(idx) => self.registers[x as usize] = idx as u8,
+                    None => self.program_counter = pc
+                }
+            }
+            Instruction::LdDtVx(x) => self.delay_timer = self.registers[x as usize],
+            Instruction::LdStVx(x) => self.sound_timer = self.registers[x as usize],
+            Instruction::AddIVx(x) => self.index_register = self.index_register.wrapping_add(self.registers[x as usize] as u16),
+            Instruction::LdFVx(x) => {
+                //font is 5 bytes per character starting at the fontset start address, only the low nibble picks the digit
+                self.index_register = (FONTSET_START_ADDRESS + 5 * (self.registers[x as usize] as usize & 0xF)) as u16;
+            }
+            Instruction::LdHfVx(x) => {
+                //10 byte hires digit
+                self.index_register = (BIG_FONTSET_START_ADDRESS + 10 * (self.registers[x as usize] as usize & 0xF)) as u16;
+            }
+            Instruction::LdBVx(x) => {
+                //BCD of Vx: hundreds at I, tens at I+1, ones at I+2
+                let value = self.registers[x as usize];
+                let index = self.memory_range(pc, self.index_register as usize, 3)?.start;
+                self.bus.write(pc, index, value / 100);
+                self.bus.write(pc, index + 1, value / 10 % 10);
+                self.bus.write(pc, index + 2, value % 10);
+            }
+            Instruction::Pitch(x) => self.pitch = self.registers[x as usize],
+            Instruction::LdIVx(x) => {
+                //store V0 through Vx in memory starting at Index
+                let range = self.memory_range(pc, self.index_register as usize, x as usize + 1)?;
+                for (address, reg) in range.zip(0..=x as usize) {
+                    self.bus.write(pc, address, self.registers[reg]);
+                }
+                if self.quirks.load_store_increments_i {
+                    //XO-CHIP's 64K of memory ends where I does, so it wraps like Fx1E
+                    self.index_register = self.index_register.wrapping_add(x as u16 + 1);
+                }
+            }
+            Instruction::LdVxI(x) => {
+                let range = self.memory_range(pc, self.index_register as usize, x as usize + 1)?;
+                for (address, reg) in range.zip(0..=x as usize) {
+                    self.registers[reg] = self.bus.read(pc, address);
+                }
+                if self.quirks.load_store_increments_i {
+                    self.index_register = self.index_register.wrapping_add(x as u16 + 1);
+                }
+            }
+            Instruction::LdRVx(x) => {
+                //HP48 RPL user flags
+                self.rpl_flags[..=x as usize].copy_from_slice(&self.registers[..=x as usize]);
+            }
+            Instruction::LdVxR(x) => self.registers[..=x as usize].copy_from_slice(&self.rpl_flags[..=x as usize]),
+        }
+        Ok(())
+    }
+
+    //8xy4-8xyE: result into Vx, then the carry/borrow/shifted out bit into VF
+    fn set_with_flag(&mut self, x: u8, value: u8, flag: bool) {
+        self.registers[x as usize] = value;
+        self.registers[0xF] = flag as u8;
+    }
+
+    //Dxyn: n byte sprite from I at (Vx, Vy), VF = collision. SCHIP Dxy0 draws a 16x16 sprite of 2 bytes per row
+    fn draw(&mut self, pc: u16, x: u8, y: u8, n: u8) -> Result<(), Chip8Error> {
+        self.registers[0xF] = 0;
+        let row_count = self.vmemory.height();
+        let col_count = self.vmemory.width();
+        let (n, width) = match n as usize {
+            0 => (16, 16),
+            n => (n, 8)
+        };
+        let bytes_per_row = width / 8;
+        let posx = self.registers[x as usize] as usize % col_count;
+        let posy = self.registers[y as usize] as usize % row_count;
+        //XO-CHIP draws one sprite per selected plane, stored back to back
+        let mut sprite_address = self.index_register as usize;
+
+        for plane in [0x1u8, 0x2] {
+            if self.planes & plane == 0 {
+                continue
+            }
+            for row_num in 0..n {
+                let row_address = sprite_address + row_num * bytes_per_row;
+                let sprite_row = self.memory_range(pc, row_address, bytes_per_row)?
+                    .fold(0u16, |row, address| (row << 8) | self.bus.read(pc, address) as u16);
+
+                for col_num in 0..width {
+                    let sprite_pixel = sprite_row & (1 << (width - 1 - col_num));
+
+                    let mut y_idx = posy + row_num;
+                    let mut x_idx = posx + col_num;
+
+                    if self.quirks.wrap_sprites {
+                        y_idx %= row_count;
+                        x_idx %= col_count;
+                    } else if y_idx > row_count - 1 || x_idx > col_count - 1 {
+                        continue
+                    }
+
+                    let mut screen_pixel = self.vmemory.get(x_idx, y_idx);
+
+                    if sprite_pixel != 0 {
+                        if screen_pixel & plane != 0 {
+                            self.registers[0xF] = 1
+                        }
+                        screen_pixel ^= plane;
+                    }
+                    self.vmemory.set(x_idx, y_idx, screen_pixel);
+                }
+            }
+            sprite_address += n * bytes_per_row;
+        }
+        if self.quirks.display_wait {
+            self.waiting_for_vblank = true;
+        }
+        self.last_draw_erased = self.registers[0xF] != 0;
+        Ok(())
+    }
+}
+
+//registers x through y inclusive, counting down when x > y
+fn register_range(x: u8, y: u8) -> Box<dyn Iterator<Item = usize>> {
+    if x <= y {
+        Box::new(x as usize..=y as usize)
+    } else {
+        Box::new((y as usize..=x as usize).rev())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::fontset::FONTSET;
+
+    fn boot(platform: Platform, source: &str) -> Processor {
+        let mut chip = Processor::new(FONTSET, platform.default_quirks(), platform);
+        chip.load_rom(&assemble(source).unwrap()).unwrap();
+        chip
+    }
+
+    #[test]
+    fn oversized_roms_are_refused() {
+        let mut chip = Processor::new(FONTSET, Platform::Chip8.default_quirks(), Platform::Chip8);
+        assert_eq!(chip.load_rom(&[0x12; 4096 - 0x200]), Ok(()));
+        assert_eq!(chip.load_rom(&[0x12; 4096 - 0x1FF]), Err(Chip8Error::RomTooLarge { size: 3585, max: 3584 }));
+        let mut chip = Processor::new(FONTSET, Platform::XoChip.default_quirks(), Platform::XoChip);
+        assert_eq!(chip.load_rom(&[0x12; 4096]), Ok(()), "XO-CHIP has 64K");
+    }
+
+    #[test]
+    fn save_state_round_trips() {
+        let source = "
+                HIGH
+                PLANE 3
+                LD V0, 0x12
+                LD V5, 0x34
+                LD DT, V0
+                LD ST, V5
+                LD I, pattern
+                AUDIO
+                PITCH V5
+                CALL draw
+                LD R, V5
+            loop:
+                ADD V1, 1
+                JP loop
+            draw:
+                LD I, pattern
+                DRW V0, V5, 4
+                RET
+            pattern:
+                DW 0xF0F0, 0x0F0F, 0xAAAA, 0x5555, 0xFFFF, 0x0000, 0x1234, 0x5678
+        ";
+        let mut chip = boot(Platform::XoChip, source);
+        chip.set_key(0xA, true);
+        chip.run_frame(20).unwrap();
+        let state = chip.save_state();
+        chip.run_frame(20).unwrap();
+        chip.set_key(0xA, false);
+        assert_ne!(chip.save_state(), state);
+
+        let mut loaded = boot(Platform::Chip8, "CLS");
+        loaded.load_state(&state).unwrap();
+        assert_eq!(loaded.save_state(), state);
+        assert_eq!((loaded.get_platform(), loaded.get_vmemory().width()), (Platform::XoChip, 128));
+        chip.load_state(&state).unwrap();
+        assert_eq!(chip.save_state(), state);
+
+        assert_eq!(chip.load_state(b"C8SX"), Err(StateError::BadMagic));
+        assert_eq!(chip.load_state(&state[..state.len() - 1]), Err(StateError::Truncated));
+        let mut newer = state.clone();
+        newer[4..6].copy_from_slice(&99u16.to_le_bytes());
+        assert_eq!(chip.load_state(&newer), Err(StateError::UnsupportedVersion(99)));
+        assert_eq!(chip.save_state(), state, "a failed load leaves the processor alone");
+    }
+
+    #[test]
+    fn execute_runs_decoded_instructions() {
+        let mut chip = boot(Platform::Chip8, "");
+        chip.execute(Instruction::LdVxByte(4, 0x2A)).unwrap();
+        chip.execute(Instruction::AddVxByte(4, 1)).unwrap();
+        assert_eq!(chip.get_registers()[4], 0x2B);
+        assert_eq!(chip.get_opcode(), 0x7401);
+        assert_eq!(chip.execute(Instruction::High), Err(Chip8Error::UnknownOpcode { pc: 0x1FE, opcode: 0x00FF }));
+    }
+}
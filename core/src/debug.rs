@@ -0,0 +1,370 @@
+//Breakpoints and stepping on top of Processor::run_frame_until. The frontend owns the Debugger and
+//calls its run_frame instead of the processor's while debugging.
+
+use std::fmt;
+use crate::bus::WatchHit;
+use crate::chip8::Processor;
+use crate::error::Chip8Error;
+use crate::instruction::{decode, Instruction};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    Register(u8),
+    Index,
+    DelayTimer,
+    SoundTimer,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+//operators in the order they have to be searched for, so "<=" isn't read as "<"
+const OPERATORS: [(&str, Comparison); 6] = [
+    ("==", Comparison::Equal), ("!=", Comparison::NotEqual), ("<=", Comparison::LessOrEqual),
+    (">=", Comparison::GreaterOrEqual), ("<", Comparison::Less), (">", Comparison::Greater)
+];
+
+//"V3 == 5", "I >= 0x300", "DT != 0"
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Condition {
+    pub operand: Operand,
+    pub comparison: Comparison,
+    pub value: u16,
+}
+
+impl Condition {
+    pub fn parse(text: &str) -> Option<Condition> {
+        let (symbol, comparison) = OPERATORS.iter().find(|(symbol, _)| text.contains(symbol))?;
+        let (left, right) = text.split_once(symbol)?;
+        let operand = match left.trim().to_uppercase().as_str() {
+            "I" => Operand::Index,
+            "DT" => Operand::DelayTimer,
+            "ST" => Operand::SoundTimer,
+            register => {
+                let index = u8::from_str_radix(register.strip_prefix('V')?, 16).ok().filter(|index| *index < 16)?;
+                Operand::Register(index)
+            }
+        };
+        let right = right.trim();
+        let value = match right.strip_prefix("0x") {
+            Some(hex) => u16::from_str_radix(hex, 16).ok()?,
+            None => right.parse().ok()?
+        };
+        Some(Condition { operand, comparison: *comparison, value })
+    }
+
+    pub fn holds(&self, chip: &Processor) -> bool {
+        let (delay, sound) = chip.get_timers();
+        let current = match self.operand {
+            Operand::Register(index) => chip.get_registers()[index as usize] as u16,
+            Operand::Index => chip.get_index_register(),
+            Operand::DelayTimer => delay as u16,
+            Operand::SoundTimer => sound as u16,
+        };
+        match self.comparison {
+            Comparison::Equal => current == self.value,
+            Comparison::NotEqual => current != self.value,
+            Comparison::Less => current < self.value,
+            Comparison::LessOrEqual => current <= self.value,
+            Comparison::Greater => current > self.value,
+            Comparison::GreaterOrEqual => current >= self.value,
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.operand {
+            Operand::Register(index) => write!(f, "V{:X}", index)?,
+            Operand::Index => write!(f, "I")?,
+            Operand::DelayTimer => write!(f, "DT")?,
+            Operand::SoundTimer => write!(f, "ST")?,
+        }
+        let symbol = OPERATORS.iter().find(|(_, comparison)| *comparison == self.comparison).unwrap().0;
+        write!(f, " {} 0x{:X}", symbol, self.value)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub address: u16,
+    pub condition: Option<Condition>,
+}
+
+//why run_frame handed control back
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    Breakpoint(u16),
+    //a step over or step out reached its return address
+    Returned,
+    //a pausing watchpoint fired, the processor is stopped just after the instruction that made the access
+    Watchpoint(WatchHit),
+}
+
+enum Target {
+    //step over: back at the instruction after the call with the stack as it was
+    Return { address: u16, stack_pointer: u16 },
+    //step out: the current subroutine has returned
+    StackBelow(u16),
+}
+
+pub struct Debugger {
+    breakpoints: Vec<Breakpoint>,
+    paused: bool,
+    target: Option<Target>,
+    //set on resume so continuing from a breakpoint doesn't stop on it again straight away
+    resuming: bool,
+    //watchpoint hits drained from the bus, logged or not
+    hits: Vec<WatchHit>,
+}
+
+impl Debugger {
+    //starts paused so the user can set breakpoints before the first instruction
+    pub fn new() -> Debugger {
+        Debugger { breakpoints: Vec::new(), paused: true, target: None, resuming: false, hits: Vec::new() }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.target = None;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.target = None;
+        self.resuming = true;
+    }
+
+    //replaces any breakpoint already at the address
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.remove_breakpoint(breakpoint.address);
+        self.breakpoints.push(breakpoint);
+        self.breakpoints.sort_by_key(|breakpoint| breakpoint.address);
+    }
+
+    //false if there was none at the address
+    pub fn remove_breakpoint(&mut self, address: u16) -> bool {
+        let count = self.breakpoints.len();
+        self.breakpoints.retain(|breakpoint| breakpoint.address != address);
+        self.breakpoints.len() != count
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    //every watchpoint hit since the last call, for the frontend to log
+    pub fn take_hits(&mut self) -> Vec<WatchHit> {
+        std::mem::take(&mut self.hits)
+    }
+
+    //runs exactly one instruction and stays paused
+    pub fn step(&mut self, chip: &mut Processor) -> Result<(), Chip8Error> {
+        self.pause();
+        let result = chip.cycle();
+        self.hits.extend(chip.get_bus_mut().take_hits());
+        result
+    }
+
+    //like step, but a 2nnn call runs until it returns
+    pub fn step_over(&mut self, chip: &mut Processor) -> Result<(), Chip8Error> {
+        let pc = chip.get_program_counter();
+        if !matches!(decode(chip.peek_word(pc)), Some(Instruction::Call(_))) {
+            return self.step(chip);
+        }
+        self.resume();
+        self.target = Some(Target::Return { address: pc.wrapping_add(2), stack_pointer: chip.get_stack_pointer() });
+        Ok(())
+    }
+
+    //runs until the current subroutine's 00EE, false when not inside one
+    pub fn step_out(&mut self, chip: &Processor) -> bool {
+        let stack_pointer = chip.get_stack_pointer();
+        if stack_pointer == 0 {
+            return false;
+        }
+        self.resume();
+        self.target = Some(Target::StackBelow(stack_pointer));
+        true
+    }
+
+    //one frame of the processor unless paused. Some(reason) when a breakpoint, watchpoint or step
+    //target was hit, leaving the debugger paused before the next instruction
+    pub fn run_frame(&mut self, chip: &mut Processor, instructions_per_frame: u32) -> Result<Option<StopReason>, Chip8Error> {
+        if self.paused {
+            return Ok(None);
+        }
+        let mut skip_breakpoints = std::mem::replace(&mut self.resuming, false);
+        let mut reason = None;
+        let (breakpoints, target) = (&self.breakpoints, &self.target);
+        let result = chip.run_frame_until(instructions_per_frame, |chip| {
+            let pc = chip.get_program_counter();
+            let reached = match target {
+                Some(Target::Return { address, stack_pointer }) => pc == *address && chip.get_stack_pointer() == *stack_pointer,
+                Some(Target::StackBelow(stack_pointer)) => chip.get_stack_pointer() < *stack_pointer,
+                None => false
+            };
+            if let Some(hit) = chip.get_bus().hits().iter().find(|hit| hit.pause) {
+                reason = Some(StopReason::Watchpoint(*hit));
+            } else if reached {
+                reason = Some(StopReason::Returned);
+            } else if !std::mem::replace(&mut skip_breakpoints, false) && breakpoints.iter()
+                .any(|breakpoint| breakpoint.address == pc && breakpoint.condition.is_none_or(|condition| condition.holds(chip))) {
+                reason = Some(StopReason::Breakpoint(pc));
+            }
+            reason.is_some()
+        });
+        let hits = chip.get_bus_mut().take_hits();
+        //the frame's last instruction can fire a watchpoint with nothing left to check it before
+        if reason.is_none() {
+            reason = hits.iter().find(|hit| hit.pause).map(|hit| StopReason::Watchpoint(*hit));
+        }
+        self.hits.extend(hits);
+        if let Err(error) = result {
+            self.pause();
+            return Err(error);
+        }
+        if reason.is_some() {
+            self.pause();
+        }
+        Ok(reason)
+    }
+}
+
+impl Default for Debugger {
+    fn default() -> Debugger {
+        Debugger::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::chip8::Platform;
+    use crate::fontset::FONTSET;
+
+    //0x200 main loop counting V0 up, 0x208 a subroutine that calls another at 0x20E
+    const ROM: &str = "
+        loop:
+            ADD V0, 1
+            CALL outer
+            JP loop
+            DW 0
+        outer:
+            ADD V1, 1
+            CALL inner
+            RET
+        inner:
+            ADD V2, 1
+            RET
+    ";
+
+    fn boot() -> (Debugger, Processor) {
+        let mut chip = Processor::new(FONTSET, Platform::Chip8.default_quirks(), Platform::Chip8);
+        chip.load_rom(&assemble(ROM).unwrap()).unwrap();
+        (Debugger::new(), chip)
+    }
+
+    #[test]
+    fn conditions_parse_and_hold() {
+        let condition = Condition::parse("vA >= 0x10").unwrap();
+        assert_eq!(condition, Condition { operand: Operand::Register(0xA), comparison: Comparison::GreaterOrEqual, value: 16 });
+        assert_eq!(condition.to_string(), "VA >= 0x10");
+        assert_eq!(Condition::parse("I<=768").map(|condition| condition.comparison), Some(Comparison::LessOrEqual));
+        assert_eq!(Condition::parse("DT != 0").map(|condition| condition.operand), Some(Operand::DelayTimer));
+        for bad in ["VG == 1", "V0 = 1", "V0 == x", "PC == 0x200"] {
+            assert_eq!(Condition::parse(bad), None, "{}", bad);
+        }
+
+        let (_, mut chip) = boot();
+        assert!(Condition::parse("V0 == 0").unwrap().holds(&chip));
+        chip.cycle().unwrap();
+        assert!(Condition::parse("V0 > 0").unwrap().holds(&chip));
+        assert!(!Condition::parse("V0 < 1").unwrap().holds(&chip));
+    }
+
+    #[test]
+    fn starts_paused_and_stops_at_breakpoints() {
+        let (mut debugger, mut chip) = boot();
+        assert_eq!(debugger.run_frame(&mut chip, 100), Ok(None));
+        assert_eq!(chip.get_cycle_count(), 0, "paused debuggers don't run the processor");
+
+        debugger.add_breakpoint(Breakpoint { address: 0x20E, condition: None });
+        debugger.resume();
+        assert_eq!(debugger.run_frame(&mut chip, 100), Ok(Some(StopReason::Breakpoint(0x20E))));
+        assert!(debugger.is_paused());
+        assert_eq!((chip.get_program_counter(), chip.get_registers()[2]), (0x20E, 0), "stops before the instruction");
+
+        //resuming runs the instruction under the breakpoint instead of stopping on it again
+        debugger.resume();
+        assert_eq!(debugger.run_frame(&mut chip, 100), Ok(Some(StopReason::Breakpoint(0x20E))));
+        assert_eq!(chip.get_registers()[0], 2);
+
+        assert!(debugger.remove_breakpoint(0x20E));
+        assert!(!debugger.remove_breakpoint(0x20E));
+        debugger.resume();
+        assert_eq!(debugger.run_frame(&mut chip, 100), Ok(None));
+    }
+
+    #[test]
+    fn conditional_breakpoints_wait_for_their_condition() {
+        let (mut debugger, mut chip) = boot();
+        debugger.add_breakpoint(Breakpoint { address: 0x200, condition: Condition::parse("V0 == 5") });
+        debugger.resume();
+        assert_eq!(debugger.run_frame(&mut chip, 1000), Ok(Some(StopReason::Breakpoint(0x200))));
+        assert_eq!(chip.get_registers()[..3], [5, 5, 5]);
+
+        //a second breakpoint at the same address replaces the first
+        debugger.add_breakpoint(Breakpoint { address: 0x200, condition: Condition::parse("V0 == 9") });
+        assert_eq!(debugger.breakpoints().len(), 1);
+        debugger.resume();
+        debugger.run_frame(&mut chip, 1000).unwrap();
+        assert_eq!(chip.get_registers()[0], 9);
+    }
+
+    #[test]
+    fn step_over_runs_calls_through() {
+        let (mut debugger, mut chip) = boot();
+        debugger.step(&mut chip).unwrap();
+        assert_eq!(chip.get_program_counter(), 0x202);
+        debugger.step_over(&mut chip).unwrap();
+        assert!(!debugger.is_paused());
+        assert_eq!(debugger.run_frame(&mut chip, 100), Ok(Some(StopReason::Returned)));
+        assert_eq!((chip.get_program_counter(), chip.get_stack_pointer()), (0x204, 0));
+        assert_eq!(chip.get_registers()[..3], [1, 1, 1]);
+
+        //anything other than a call is a plain step
+        debugger.step_over(&mut chip).unwrap();
+        assert!(debugger.is_paused());
+        assert_eq!(chip.get_program_counter(), 0x200);
+    }
+
+    #[test]
+    fn step_out_runs_to_the_callers_return() {
+        let (mut debugger, mut chip) = boot();
+        assert!(!debugger.step_out(&chip), "nothing to step out of at the top level");
+        debugger.add_breakpoint(Breakpoint { address: 0x20E, condition: None });
+        debugger.resume();
+        debugger.run_frame(&mut chip, 100).unwrap();
+        assert_eq!(chip.get_stack_pointer(), 2);
+
+        assert!(debugger.step_out(&chip));
+        assert_eq!(debugger.run_frame(&mut chip, 100), Ok(Some(StopReason::Returned)));
+        assert_eq!((chip.get_program_counter(), chip.get_stack_pointer()), (0x20C, 1));
+        assert!(debugger.step_out(&chip));
+        assert_eq!(debugger.run_frame(&mut chip, 100), Ok(Some(StopReason::Returned)));
+        assert_eq!((chip.get_program_counter(), chip.get_stack_pointer()), (0x204, 0));
+    }
+}
@@ -0,0 +1,374 @@
+//Disassembler for CHIP-8, SCHIP 1.1 and XO-CHIP. mnemonic renders a single opcode for the
+//debugger; disassemble walks a whole ROM by following jumps, calls and skips from the entry point
+//so only reachable bytes are listed as code and everything else comes out as data.
+
+use std::collections::{BTreeMap, BTreeSet};
+use crate::chip8::Platform;
+use crate::instruction::{decode, Instruction};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Syntax {
+    //LD V3, 0x1F as in Cowgod's technical reference
+    Cowgod,
+    //v3 := 0x1F, output can be fed back into Octo
+    Octo,
+}
+
+impl Syntax {
+    pub fn from_name(name: &str) -> Option<Syntax> {
+        match name.to_lowercase().as_str() {
+            "cowgod" => Some(Syntax::Cowgod),
+            "octo" => Some(Syntax::Octo),
+            _ => None
+        }
+    }
+}
+
+//`next_word` is only read by XO-CHIP F000 nnnn, whose address lives in the following word
+pub fn mnemonic(opcode: u16, next_word: u16) -> String {
+    match decode(opcode) {
+        Some(instruction) => render(instruction, next_word, Syntax::Cowgod, &BTreeMap::new()),
+        None => format!("DW 0x{:04X}", opcode)
+    }
+}
+
+//listing of `data` loaded at `origin`, analysed from origin as the entry point
+pub fn disassemble(data: &[u8], origin: u16, platform: Platform, syntax: Syntax) -> String {
+    //addresses are 16 bit, anything past the top of memory can't be loaded anyway
+    let data = &data[..data.len().min(0x10000 - origin as usize)];
+    let (code, labels) = analyse(data, origin, platform);
+    let mut out = String::new();
+    //the assembler starts at 0x200 like the interpreter
+    if syntax == Syntax::Cowgod && origin != 0x200 {
+        out.push_str(&format!("org 0x{:03X}\n", origin));
+    }
+    let end = origin as usize + data.len();
+    let mut address = origin as usize;
+    while address < end {
+        if let Some(label) = labels.get(&(address as u16)) {
+            match syntax {
+                Syntax::Cowgod => out.push_str(&format!("{}:\n", label)),
+                Syntax::Octo => out.push_str(&format!(": {}\n", label)),
+            }
+        }
+        let offset = address - origin as usize;
+        if code.contains(&(address as u16)) {
+            //analyse only marks addresses that decode
+            let instruction = decode(word(data, offset)).unwrap();
+            let length = instruction.length();
+            let text = render(instruction, word(data, offset + 2), syntax, &labels);
+            match syntax {
+                Syntax::Cowgod => {
+                    let bytes: String = data[offset..offset + length].iter().map(|byte| format!("{:02X}", byte)).collect();
+                    out.push_str(&format!("0x{:03X}  {:<8}  {}\n", address, bytes, text));
+                }
+                Syntax::Octo => out.push_str(&format!("\t{}\n", text)),
+            }
+            address += length;
+            continue;
+        }
+        //data runs up to 8 bytes, broken at the next label or instruction
+        let mut run_end = address + 1;
+        while run_end < end && run_end - address < 8 && !code.contains(&(run_end as u16)) && !labels.contains_key(&(run_end as u16)) {
+            run_end += 1;
+        }
+        let bytes = &data[offset..run_end - origin as usize];
+        match syntax {
+            Syntax::Cowgod => {
+                let hex: String = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+                let values: Vec<String> = bytes.iter().map(|byte| format!("0x{:02X}", byte)).collect();
+                out.push_str(&format!("0x{:03X}  {:<8}  DB {}\n", address, hex, values.join(", ")));
+            }
+            Syntax::Octo => {
+                let values: Vec<String> = bytes.iter().map(|byte| format!("0x{:02X}", byte)).collect();
+                out.push_str(&format!("\t{}\n", values.join(" ")));
+            }
+        }
+        address = run_end;
+    }
+    out
+}
+
+//big endian word at offset, 0 past the end
+fn word(data: &[u8], offset: usize) -> u16 {
+    match data.get(offset..offset + 2) {
+        Some(bytes) => ((bytes[0] as u16) << 8) | bytes[1] as u16,
+        None => 0
+    }
+}
+
+//addresses that start an instruction, and names for everything jumped to, called or pointed at by I
+fn analyse(data: &[u8], origin: u16, platform: Platform) -> (BTreeSet<u16>, BTreeMap<u16, String>) {
+    let start = origin as usize;
+    let end = start + data.len();
+    let in_rom = |address: usize| (start..end).contains(&address);
+    let mut code = BTreeSet::new();
+    let mut covered = vec![false; data.len()];
+    let mut calls = BTreeSet::new();
+    let mut jumps = BTreeSet::new();
+    let mut pointers = BTreeSet::new();
+    let mut pending = vec![origin as usize];
+
+    while let Some(address) = pending.pop() {
+        if !in_rom(address) || address + 1 >= end {
+            continue;
+        }
+        let Some(instruction) = decode(word(data, address - start)).filter(|instruction| instruction.is_supported_on(platform)) else {
+            continue;
+        };
+        let length = instruction.length();
+        //overlapping another instruction means one of the two paths is really data
+        if address + length > end || covered[address - start..address - start + length].contains(&true) {
+            continue;
+        }
+        code.insert(address as u16);
+        covered[address - start..address - start + length].fill(true);
+
+        let next = address + length;
+        match instruction {
+            Instruction::LdI(target) => {
+                pointers.insert(target as usize);
+            }
+            Instruction::LdILong => {
+                pointers.insert(word(data, address - start + 2) as usize);
+            }
+            _ => {}
+        }
+        match instruction {
+            Instruction::Jp(target) => {
+                jumps.insert(target as usize);
+                pending.push(target as usize);
+            }
+            Instruction::Call(target) => {
+                calls.insert(target as usize);
+                pending.push(target as usize);
+                pending.push(next);
+            }
+            //either the next instruction or the one after it runs
+            skip if skip.is_skip() => {
+                pending.push(next);
+                let skipped = decode(word(data, next - start)).filter(|instruction| instruction.is_supported_on(platform))
+                    .map_or(2, |instruction| instruction.length());
+                pending.push(next + skipped);
+            }
+            //nothing after returns, exits and computed jumps is known to be reached
+            Instruction::Ret | Instruction::Exit | Instruction::JpV0(_) => {}
+            _ => pending.push(next)
+        }
+    }
+
+    //a label is only useful where a listing line starts
+    let starts_line = |address: &usize| in_rom(*address) && (code.contains(&(*address as u16)) || !covered[*address - start]);
+    let mut labels = BTreeMap::new();
+    for address in pointers.iter().filter(|address| starts_line(address)) {
+        labels.insert(*address as u16, format!("data_{:03X}", address));
+    }
+    for address in jumps.iter().filter(|address| starts_line(address)) {
+        labels.insert(*address as u16, format!("label_{:03X}", address));
+    }
+    for address in calls.iter().filter(|address| starts_line(address)) {
+        labels.insert(*address as u16, format!("sub_{:03X}", address));
+    }
+    labels.insert(origin, "main".to_string());
+    (code, labels)
+}
+
+fn render(instruction: Instruction, next_word: u16, syntax: Syntax, labels: &BTreeMap<u16, String>) -> String {
+    use Instruction::*;
+    let target = |address: u16, digits: usize| labels.get(&address).cloned().unwrap_or_else(|| format!("0x{:0digits$X}", address, digits = digits));
+
+    match syntax {
+        Syntax::Cowgod => match instruction {
+            Sys(nnn) => format!("SYS 0x{:03X}", nnn),
+            Cls => "CLS".to_string(),
+            Ret => "RET".to_string(),
+            Scd(n) => format!("SCD {}", n),
+            Scu(n) => format!("SCU {}", n),
+            Scr => "SCR".to_string(),
+            Scl => "SCL".to_string(),
+            Exit => "EXIT".to_string(),
+            Low => "LOW".to_string(),
+            High => "HIGH".to_string(),
+            Jp(nnn) => format!("JP {}", target(nnn, 3)),
+            Call(nnn) => format!("CALL {}", target(nnn, 3)),
+            SeVxByte(x, kk) => format!("SE V{:X}, 0x{:02X}", x, kk),
+            SneVxByte(x, kk) => format!("SNE V{:X}, 0x{:02X}", x, kk),
+            SeVxVy(x, y) => format!("SE V{:X}, V{:X}", x, y),
+            Save(x, y) => format!("SAVE V{:X}, V{:X}", x, y),
+            Load(x, y) => format!("LOAD V{:X}, V{:X}", x, y),
+            LdVxByte(x, kk) => format!("LD V{:X}, 0x{:02X}", x, kk),
+            AddVxByte(x, kk) => format!("ADD V{:X}, 0x{:02X}", x, kk),
+            LdVxVy(x, y) => format!("LD V{:X}, V{:X}", x, y),
+            Or(x, y) => format!("OR V{:X}, V{:X}", x, y),
+            And(x, y) => format!("AND V{:X}, V{:X}", x, y),
+            Xor(x, y) => format!("XOR V{:X}, V{:X}", x, y),
+            AddVxVy(x, y) => format!("ADD V{:X}, V{:X}", x, y),
+            Sub(x, y) => format!("SUB V{:X}, V{:X}", x, y),
+            Shr(x, y) => format!("SHR V{:X}, V{:X}", x, y),
+            Subn(x, y) => format!("SUBN V{:X}, V{:X}", x, y),
+            Shl(x, y) => format!("SHL V{:X}, V{:X}", x, y),
+            SneVxVy(x, y) => format!("SNE V{:X}, V{:X}", x, y),
+            LdI(nnn) => format!("LD I, {}", target(nnn, 3)),
+            JpV0(nnn) => format!("JP V0, {}", target(nnn, 3)),
+            Rnd(x, kk) => format!("RND V{:X}, 0x{:02X}", x, kk),
+            Drw { x, y, n } => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+            Skp(x) => format!("SKP V{:X}", x),
+            Sknp(x) => format!("SKNP V{:X}", x),
+            LdILong => format!("LD I, LONG {}", target(next_word, 4)),
+            Plane(n) => format!("PLANE {}", n),
+            Audio => "AUDIO".to_string(),
+            LdVxDt(x) => format!("LD V{:X}, DT", x),
+            LdVxK(x) => format!("LD V{:X}, K", x),
+            LdDtVx(x) => format!("LD DT, V{:X}", x),
+            LdStVx(x) => format!("LD ST, V{:X}", x),
+            AddIVx(x) => format!("ADD I, V{:X}", x),
+            LdFVx(x) => format!("LD F, V{:X}", x),
+            LdHfVx(x) => format!("LD HF, V{:X}", x),
+            LdBVx(x) => format!("LD B, V{:X}", x),
+            Pitch(x) => format!("PITCH V{:X}", x),
+            LdIVx(x) => format!("LD [I], V{:X}", x),
+            LdVxI(x) => format!("LD V{:X}, [I]", x),
+            LdRVx(x) => format!("LD R, V{:X}", x),
+            LdVxR(x) => format!("LD V{:X}, R", x),
+        },
+        Syntax::Octo => match instruction {
+            //Octo has no machine code calls, keep the raw bytes
+            Sys(nnn) => format!("0x{:02X} 0x{:02X}", nnn >> 8, nnn & 0xFF),
+            Cls => "clear".to_string(),
+            Ret => "return".to_string(),
+            Scd(n) => format!("scroll-down {}", n),
+            Scu(n) => format!("scroll-up {}", n),
+            Scr => "scroll-right".to_string(),
+            Scl => "scroll-left".to_string(),
+            Exit => "exit".to_string(),
+            Low => "lores".to_string(),
+            High => "hires".to_string(),
+            Jp(nnn) => format!("jump {}", target(nnn, 3)),
+            Call(nnn) => match labels.get(&nnn) {
+                Some(label) => label.clone(),
+                None => format!(":call 0x{:03X}", nnn)
+            },
+            //skips read inverted in Octo: 'if v3 != 0x1F then' assembles to 3xkk
+            SeVxByte(x, kk) => format!("if v{:x} != 0x{:02X} then", x, kk),
+            SneVxByte(x, kk) => format!("if v{:x} == 0x{:02X} then", x, kk),
+            SeVxVy(x, y) => format!("if v{:x} != v{:x} then", x, y),
+            Save(x, y) => format!("save v{:x} - v{:x}", x, y),
+            Load(x, y) => format!("load v{:x} - v{:x}", x, y),
+            LdVxByte(x, kk) => format!("v{:x} := 0x{:02X}", x, kk),
+            AddVxByte(x, kk) => format!("v{:x} += 0x{:02X}", x, kk),
+            LdVxVy(x, y) => format!("v{:x} := v{:x}", x, y),
+            Or(x, y) => format!("v{:x} |= v{:x}", x, y),
+            And(x, y) => format!("v{:x} &= v{:x}", x, y),
+            Xor(x, y) => format!("v{:x} ^= v{:x}", x, y),
+            AddVxVy(x, y) => format!("v{:x} += v{:x}", x, y),
+            Sub(x, y) => format!("v{:x} -= v{:x}", x, y),
+            Shr(x, y) => format!("v{:x} >>= v{:x}", x, y),
+            Subn(x, y) => format!("v{:x} =- v{:x}", x, y),
+            Shl(x, y) => format!("v{:x} <<= v{:x}", x, y),
+            SneVxVy(x, y) => format!("if v{:x} == v{:x} then", x, y),
+            LdI(nnn) => format!("i := {}", target(nnn, 3)),
+            JpV0(nnn) => format!("jump0 {}", target(nnn, 3)),
+            Rnd(x, kk) => format!("v{:x} := random 0x{:02X}", x, kk),
+            Drw { x, y, n } => format!("sprite v{:x} v{:x} {}", x, y, n),
+            Skp(x) => format!("if v{:x} -key then", x),
+            Sknp(x) => format!("if v{:x} key then", x),
+            LdILong => format!("i := long {}", target(next_word, 4)),
+            Plane(n) => format!("plane {}", n),
+            Audio => "audio".to_string(),
+            LdVxDt(x) => format!("v{:x} := delay", x),
+            LdVxK(x) => format!("v{:x} := key", x),
+            LdDtVx(x) => format!("delay := v{:x}", x),
+            LdStVx(x) => format!("buzzer := v{:x}", x),
+            AddIVx(x) => format!("i += v{:x}", x),
+            LdFVx(x) => format!("i := hex v{:x}", x),
+            LdHfVx(x) => format!("i := bighex v{:x}", x),
+            LdBVx(x) => format!("bcd v{:x}", x),
+            Pitch(x) => format!("pitch := v{:x}", x),
+            LdIVx(x) => format!("save v{:x}", x),
+            LdVxI(x) => format!("load v{:x}", x),
+            LdRVx(x) => format!("saveflags v{:x}", x),
+            LdVxR(x) => format!("loadflags v{:x}", x),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //a call, a jump, a skip and a pointer into data, followed by bytes nothing reaches
+    const ROM: [u8; 20] = [
+        0x60, 0x05, //LD V0, 5
+        0xA2, 0x0E, //LD I, 0x20E
+        0x22, 0x08, //CALL 0x208
+        0x12, 0x06, //JP 0x206
+        0x30, 0x01, //SE V0, 1
+        0x00, 0xE0, //CLS
+        0x00, 0xEE, //RET
+        0xF0, 0x90, //sprite
+        0x12, 0x34, 0x00, 0xE0, //never reached, though it would decode
+    ];
+
+    #[test]
+    fn follows_jumps_calls_and_skips() {
+        let (code, labels) = analyse(&ROM, 0x200, Platform::Chip8);
+        assert_eq!(code.into_iter().collect::<Vec<_>>(), [0x200, 0x202, 0x204, 0x206, 0x208, 0x20A, 0x20C]);
+        let names: Vec<(u16, &str)> = labels.iter().map(|(address, name)| (*address, name.as_str())).collect();
+        assert_eq!(names, [(0x200, "main"), (0x206, "label_206"), (0x208, "sub_208"), (0x20E, "data_20E")]);
+    }
+
+    #[test]
+    fn cowgod_listing() {
+        assert_eq!(disassemble(&ROM, 0x200, Platform::Chip8, Syntax::Cowgod), "\
+main:
+0x200  6005      LD V0, 0x05
+0x202  A20E      LD I, data_20E
+0x204  2208      CALL sub_208
+label_206:
+0x206  1206      JP label_206
+sub_208:
+0x208  3001      SE V0, 0x01
+0x20A  00E0      CLS
+0x20C  00EE      RET
+data_20E:
+0x20E  F090123400E0  DB 0xF0, 0x90, 0x12, 0x34, 0x00, 0xE0
+");
+    }
+
+    #[test]
+    fn octo_listing() {
+        assert_eq!(disassemble(&ROM, 0x200, Platform::Chip8, Syntax::Octo), "\
+: main
+\tv0 := 0x05
+\ti := data_20E
+\tsub_208
+: label_206
+\tjump label_206
+: sub_208
+\tif v0 != 0x01 then
+\tclear
+\treturn
+: data_20E
+\t0xF0 0x90 0x12 0x34 0x00 0xE0
+");
+    }
+
+    #[test]
+    fn unsupported_and_overlapping_code_is_data() {
+        //F000 nnnn is XO-CHIP only, so on CHIP-8 the path stops there
+        let rom = [0xF0, 0x00, 0x12, 0x00];
+        assert_eq!(analyse(&rom, 0x200, Platform::Chip8).0.len(), 0);
+        assert_eq!(analyse(&rom, 0x200, Platform::XoChip).0.into_iter().collect::<Vec<_>>(), [0x200]);
+        //a jump into the middle of an instruction isn't followed
+        let rom = [0x60, 0x12, 0x12, 0x01];
+        assert_eq!(analyse(&rom, 0x200, Platform::Chip8).0.into_iter().collect::<Vec<_>>(), [0x200, 0x202]);
+    }
+
+    #[test]
+    fn single_mnemonics() {
+        assert_eq!(mnemonic(0xD125, 0), "DRW V1, V2, 5");
+        assert_eq!(mnemonic(0xF000, 0x1234), "LD I, LONG 0x1234");
+        assert_eq!(mnemonic(0x5121, 0), "DW 0x5121");
+        assert_eq!(Syntax::from_name("OCTO"), Some(Syntax::Octo));
+    }
+}
@@ -0,0 +1,53 @@
+//Text/image/hash renderings of a framebuffer for headless runs and regression tests.
+
+use crate::framebuffer::Framebuffer;
+
+//one character per pixel: '.' off, '#' lit; XO-CHIP plane 2 only is '+' and both planes '@'
+pub fn ascii(framebuffer: &Framebuffer) -> String {
+    let mut out = String::with_capacity((framebuffer.width() + 1) * framebuffer.height());
+    for y in 0..framebuffer.height() {
+        for x in 0..framebuffer.width() {
+            out.push(match framebuffer.get(x, y) {
+                0 => '.',
+                1 => '#',
+                2 => '+',
+                _ => '@'
+            });
+        }
+        out.push('\n');
+    }
+    out
+}
+
+//plain (P1) portable bitmap, any lit plane counts as black
+pub fn pbm(framebuffer: &Framebuffer) -> String {
+    let mut out = format!("P1\n{} {}\n", framebuffer.width(), framebuffer.height());
+    for y in 0..framebuffer.height() {
+        let row: Vec<&str> = (0..framebuffer.width())
+            .map(|x| if framebuffer.get(x, y) != 0 { "1" } else { "0" })
+            .collect();
+        out.push_str(&row.join(" "));
+        out.push('\n');
+    }
+    out
+}
+
+//64 bit FNV-1a over the resolution and every pixel, stable across runs and platforms
+pub fn hash(framebuffer: &Framebuffer) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let size = [framebuffer.width() as u8, framebuffer.height() as u8];
+    for y in 0..framebuffer.height() {
+        for x in 0..framebuffer.width() {
+            hash_byte(&mut hash, framebuffer.get(x, y));
+        }
+    }
+    for byte in size {
+        hash_byte(&mut hash, byte);
+    }
+    hash
+}
+
+fn hash_byte(hash: &mut u64, byte: u8) {
+    *hash ^= byte as u64;
+    *hash = hash.wrapping_mul(0x100000001b3);
+}
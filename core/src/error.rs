@@ -0,0 +1,32 @@
+use std::fmt;
+
+//Faults raised by Processor::cycle, and by load_rom. `pc` is the address of the faulting instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chip8Error {
+    UnknownOpcode { pc: u16, opcode: u16 },
+    //2nnn with all 16 stack slots in use
+    StackOverflow { pc: u16, opcode: u16 },
+    //00EE with nothing on the stack
+    StackUnderflow { pc: u16, opcode: u16 },
+    //instruction touched memory at or past the end of RAM
+    MemoryOutOfBounds { pc: u16, opcode: u16, address: usize },
+    //program counter left RAM so no opcode could be fetched
+    PcOutOfBounds { pc: u16 },
+    //ROM doesn't fit between the load address and the end of RAM
+    RomTooLarge { size: usize, max: usize },
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chip8Error::UnknownOpcode { pc, opcode } => write!(f, "unknown opcode {:04X} at 0x{:03X}", opcode, pc),
+            Chip8Error::StackOverflow { pc, opcode } => write!(f, "stack overflow: {:04X} at 0x{:03X} called with 16 nested subroutines", opcode, pc),
+            Chip8Error::StackUnderflow { pc, opcode } => write!(f, "stack underflow: {:04X} at 0x{:03X} returned with an empty stack", opcode, pc),
+            Chip8Error::MemoryOutOfBounds { pc, opcode, address } => write!(f, "memory access out of bounds: {:04X} at 0x{:03X} touched 0x{:X}", opcode, pc, address),
+            Chip8Error::PcOutOfBounds { pc } => write!(f, "program counter out of bounds at 0x{:03X}", pc),
+            Chip8Error::RomTooLarge { size, max } => write!(f, "ROM is {} bytes but only {} fit in memory", size, max),
+        }
+    }
+}
+
+impl std::error::Error for Chip8Error {}
@@ -0,0 +1,95 @@
+//Resizable display memory. CHIP-8 runs at 64x32 (lores); SUPER-CHIP adds a 128x64 hires mode
+//and XO-CHIP adds a second bitplane.
+
+pub const LORES_WIDTH: usize = 64;
+pub const LORES_HEIGHT: usize = 32;
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    //row-major, one byte per pixel holding a bitmask of the XO-CHIP planes that are lit
+    pixels: Vec<u8>,
+}
+
+impl Framebuffer {
+    pub fn new() -> Framebuffer {
+        Framebuffer { width: LORES_WIDTH, height: LORES_HEIGHT, pixels: vec![0; LORES_WIDTH * LORES_HEIGHT] }
+    }
+
+    //None unless width x height is one of the two supported resolutions and matches pixels
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<u8>) -> Option<Framebuffer> {
+        let supported = [(LORES_WIDTH, LORES_HEIGHT), (HIRES_WIDTH, HIRES_HEIGHT)].contains(&(width, height));
+        if !supported || pixels.len() != width * height {
+            return None;
+        }
+        Some(Framebuffer { width, height, pixels })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    //switching resolution clears the screen like SCHIP 1.1 does
+    pub fn set_hires(&mut self, hires: bool) {
+        (self.width, self.height) = if hires { (HIRES_WIDTH, HIRES_HEIGHT) } else { (LORES_WIDTH, LORES_HEIGHT) };
+        self.pixels = vec![0; self.width * self.height];
+    }
+
+    //only the bitplanes set in `planes` are touched, the others keep their pixels
+    pub fn clear(&mut self, planes: u8) {
+        for pixel in self.pixels.iter_mut() {
+            *pixel &= !planes;
+        }
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width + x]
+    }
+    pub fn set(&mut self, x: usize, y: usize, value: u8) {
+        self.pixels[y * self.width + x] = value;
+    }
+
+    pub fn scroll_down(&mut self, rows: usize, planes: u8) {
+        self.scroll(0, rows as isize, planes);
+    }
+    pub fn scroll_up(&mut self, rows: usize, planes: u8) {
+        self.scroll(0, -(rows as isize), planes);
+    }
+    pub fn scroll_right(&mut self, cols: usize, planes: u8) {
+        self.scroll(cols as isize, 0, planes);
+    }
+    pub fn scroll_left(&mut self, cols: usize, planes: u8) {
+        self.scroll(-(cols as isize), 0, planes);
+    }
+
+    fn scroll(&mut self, dx: isize, dy: isize, planes: u8) {
+        let old = self.pixels.clone();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (src_x, src_y) = (x as isize - dx, y as isize - dy);
+                let moved = if src_x < 0 || src_y < 0 || src_x >= self.width as isize || src_y >= self.height as isize {
+                    0
+                } else {
+                    old[src_y as usize * self.width + src_x as usize] & planes
+                };
+                let idx = y * self.width + x;
+                self.pixels[idx] = (old[idx] & !planes) | moved;
+            }
+        }
+    }
+}
+
+impl Default for Framebuffer {
+    fn default() -> Framebuffer {
+        Framebuffer::new()
+    }
+}
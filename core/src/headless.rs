@@ -0,0 +1,117 @@
+//The run loop behind chip8rs --headless and chip8-headless: frames back to back, with no window,
+//sound or frame pacing. Keys, movies and traces are the caller's, through Hooks.
+
+use crate::chip8::Processor;
+use crate::error::Chip8Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    Frames(u64),
+    //instructions rather than frames, the timers still tick every instructions_per_frame of them
+    Cycles(u64),
+}
+
+//called around every frame, both do nothing unless overridden
+pub trait Hooks {
+    //before frame `frame` (counting from 0) runs, to set the keys. false ends the run there
+    fn before_frame(&mut self, _chip: &mut Processor, _frame: u64) -> bool {
+        true
+    }
+    //after a frame has run, `frames` counting it
+    fn after_frame(&mut self, _chip: &mut Processor, _frames: u64) {}
+}
+
+//runs until the limit, SCHIP exit, a fault or before_frame says stop. the frames finished on success
+pub fn run<H: Hooks>(chip: &mut Processor, instructions_per_frame: u32, limit: Limit, hooks: &mut H) -> Result<u64, Chip8Error> {
+    match limit {
+        Limit::Frames(frames) => {
+            for frame in 0..frames {
+                if chip.has_exited() || !hooks.before_frame(chip, frame) {
+                    return Ok(frame);
+                }
+                chip.run_frame(instructions_per_frame)?;
+                hooks.after_frame(chip, frame + 1);
+            }
+            Ok(frames)
+        }
+        Limit::Cycles(cycles) => {
+            let instructions_per_frame = instructions_per_frame.max(1) as u64;
+            let mut frame = 0;
+            for cycle in 0..cycles {
+                if cycle % instructions_per_frame == 0 && !hooks.before_frame(chip, frame) {
+                    break;
+                }
+                if chip.has_exited() {
+                    break;
+                }
+                chip.cycle()?;
+                if (cycle + 1) % instructions_per_frame == 0 {
+                    chip.tick_timers();
+                    frame += 1;
+                    hooks.after_frame(chip, frame);
+                }
+            }
+            Ok(frame)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::chip8::Platform;
+    use crate::fontset::FONTSET;
+
+    //counts frames and stops before `stop_at`
+    struct Counter {
+        before: Vec<u64>,
+        after: Vec<u64>,
+        stop_at: u64,
+    }
+
+    impl Hooks for Counter {
+        fn before_frame(&mut self, _chip: &mut Processor, frame: u64) -> bool {
+            self.before.push(frame);
+            frame < self.stop_at
+        }
+        fn after_frame(&mut self, _chip: &mut Processor, frames: u64) {
+            self.after.push(frames);
+        }
+    }
+
+    fn boot(source: &str) -> Processor {
+        let mut chip = Processor::new(FONTSET, Platform::XoChip.default_quirks(), Platform::XoChip);
+        chip.load_rom(&assemble(source).unwrap()).unwrap();
+        chip
+    }
+
+    #[test]
+    fn frames() {
+        let mut chip = boot("loop: ADD V0, 1\nJP loop");
+        let mut counter = Counter { before: Vec::new(), after: Vec::new(), stop_at: u64::MAX };
+        assert_eq!(run(&mut chip, 10, Limit::Frames(3), &mut counter), Ok(3));
+        assert_eq!((counter.before, counter.after), (vec![0, 1, 2], vec![1, 2, 3]));
+        assert_eq!(chip.get_cycle_count(), 30);
+
+        let mut counter = Counter { before: Vec::new(), after: Vec::new(), stop_at: 1 };
+        assert_eq!(run(&mut chip, 10, Limit::Frames(3), &mut counter), Ok(1), "before_frame stops the run");
+    }
+
+    #[test]
+    fn cycles_tick_the_timers() {
+        let mut chip = boot("LD V0, 5\nLD DT, V0\nloop: JP loop");
+        let mut counter = Counter { before: Vec::new(), after: Vec::new(), stop_at: u64::MAX };
+        assert_eq!(run(&mut chip, 4, Limit::Cycles(10), &mut counter), Ok(2));
+        assert_eq!((counter.before, counter.after), (vec![0, 1, 2], vec![1, 2]));
+        assert_eq!((chip.get_cycle_count(), chip.get_timers().0), (10, 3));
+    }
+
+    #[test]
+    fn exits_and_faults_end_the_run() {
+        let mut chip = boot("EXIT");
+        assert_eq!(run(&mut chip, 10, Limit::Frames(5), &mut Counter { before: Vec::new(), after: Vec::new(), stop_at: u64::MAX }), Ok(1));
+        let mut chip = boot("RET");
+        assert!(run(&mut chip, 10, Limit::Cycles(5), &mut Counter { before: Vec::new(), after: Vec::new(), stop_at: u64::MAX }).is_err());
+    }
+}
@@ -0,0 +1,310 @@
+//Typed opcodes. decode and Instruction::encode are the only places that know the bit layout; the
+//processor, disassembler, assembler and tracer all work on Instruction. Names follow the Cowgod mnemonics.
+
+use crate::chip8::Platform;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Instruction {
+    //0nnn machine code routine, decodes so it can be listed but never executes
+    Sys(u16),
+    Cls,
+    Ret,
+    //SCHIP
+    Scd(u8),
+    Scr,
+    Scl,
+    Exit,
+    Low,
+    High,
+    //XO-CHIP 00Dn
+    Scu(u8),
+    Jp(u16),
+    Call(u16),
+    SeVxByte(u8, u8),
+    SneVxByte(u8, u8),
+    SeVxVy(u8, u8),
+    //XO-CHIP 5xy2 / 5xy3
+    Save(u8, u8),
+    Load(u8, u8),
+    LdVxByte(u8, u8),
+    AddVxByte(u8, u8),
+    LdVxVy(u8, u8),
+    Or(u8, u8),
+    And(u8, u8),
+    Xor(u8, u8),
+    AddVxVy(u8, u8),
+    Sub(u8, u8),
+    Shr(u8, u8),
+    Subn(u8, u8),
+    Shl(u8, u8),
+    SneVxVy(u8, u8),
+    LdI(u16),
+    //Bnnn, or Bxnn with the jump_uses_vx quirk
+    JpV0(u16),
+    Rnd(u8, u8),
+    Drw { x: u8, y: u8, n: u8 },
+    Skp(u8),
+    Sknp(u8),
+    //XO-CHIP F000 nnnn, the address is the word after the opcode
+    LdILong,
+    Plane(u8),
+    Audio,
+    LdVxDt(u8),
+    LdVxK(u8),
+    LdDtVx(u8),
+    LdStVx(u8),
+    AddIVx(u8),
+    LdFVx(u8),
+    //SCHIP Fx30
+    LdHfVx(u8),
+    LdBVx(u8),
+    //XO-CHIP Fx3A
+    Pitch(u8),
+    //Fx55 LD [I], Vx
+    LdIVx(u8),
+    //Fx65 LD Vx, [I]
+    LdVxI(u8),
+    //SCHIP Fx75 / Fx85
+    LdRVx(u8),
+    LdVxR(u8),
+}
+
+//None for opcodes no supported platform defines
+pub fn decode(opcode: u16) -> Option<Instruction> {
+    use Instruction::*;
+    let x = ((opcode & 0x0F00) >> 8) as u8;
+    let y = ((opcode & 0x00F0) >> 4) as u8;
+    let n = (opcode & 0x000F) as u8;
+    let kk = (opcode & 0x00FF) as u8;
+    let nnn = opcode & 0x0FFF;
+
+    let instruction = match (opcode >> 12, x, y, n) {
+        (0x0, 0x0, 0xE, 0x0) => Cls,
+        (0x0, 0x0, 0xE, 0xE) => Ret,
+        (0x0, 0x0, 0xC, _) => Scd(n),
+        (0x0, 0x0, 0xD, _) => Scu(n),
+        (0x0, 0x0, 0xF, 0xB) => Scr,
+        (0x0, 0x0, 0xF, 0xC) => Scl,
+        (0x0, 0x0, 0xF, 0xD) => Exit,
+        (0x0, 0x0, 0xF, 0xE) => Low,
+        (0x0, 0x0, 0xF, 0xF) => High,
+        (0x0, ..) => Sys(nnn),
+        (0x1, ..) => Jp(nnn),
+        (0x2, ..) => Call(nnn),
+        (0x3, ..) => SeVxByte(x, kk),
+        (0x4, ..) => SneVxByte(x, kk),
+        (0x5, _, _, 0x0) => SeVxVy(x, y),
+        (0x5, _, _, 0x2) => Save(x, y),
+        (0x5, _, _, 0x3) => Load(x, y),
+        (0x6, ..) => LdVxByte(x, kk),
+        (0x7, ..) => AddVxByte(x, kk),
+        (0x8, _, _, 0x0) => LdVxVy(x, y),
+        (0x8, _, _, 0x1) => Or(x, y),
+        (0x8, _, _, 0x2) => And(x, y),
+        (0x8, _, _, 0x3) => Xor(x, y),
+        (0x8, _, _, 0x4) => AddVxVy(x, y),
+        (0x8, _, _, 0x5) => Sub(x, y),
+        (0x8, _, _, 0x6) => Shr(x, y),
+        (0x8, _, _, 0x7) => Subn(x, y),
+        (0x8, _, _, 0xE) => Shl(x, y),
+        (0x9, _, _, 0x0) => SneVxVy(x, y),
+        (0xA, ..) => LdI(nnn),
+        (0xB, ..) => JpV0(nnn),
+        (0xC, ..) => Rnd(x, kk),
+        (0xD, ..) => Drw { x, y, n },
+        (0xE, _, 0x9, 0xE) => Skp(x),
+        (0xE, _, 0xA, 0x1) => Sknp(x),
+        (0xF, 0x0, 0x0, 0x0) => LdILong,
+        (0xF, _, 0x0, 0x1) => Plane(x),
+        (0xF, 0x0, 0x0, 0x2) => Audio,
+        (0xF, _, 0x0, 0x7) => LdVxDt(x),
+        (0xF, _, 0x0, 0xA) => LdVxK(x),
+        (0xF, _, 0x1, 0x5) => LdDtVx(x),
+        (0xF, _, 0x1, 0x8) => LdStVx(x),
+        (0xF, _, 0x1, 0xE) => AddIVx(x),
+        (0xF, _, 0x2, 0x9) => LdFVx(x),
+        (0xF, _, 0x3, 0x0) => LdHfVx(x),
+        (0xF, _, 0x3, 0x3) => LdBVx(x),
+        (0xF, _, 0x3, 0xA) => Pitch(x),
+        (0xF, _, 0x5, 0x5) => LdIVx(x),
+        (0xF, _, 0x6, 0x5) => LdVxI(x),
+        (0xF, _, 0x7, 0x5) => LdRVx(x),
+        (0xF, _, 0x8, 0x5) => LdVxR(x),
+        _ => return None
+    };
+    Some(instruction)
+}
+
+impl Instruction {
+    //inverse of decode. fields wider than their slot in the opcode are masked
+    pub fn encode(&self) -> u16 {
+        use Instruction::*;
+        let xy = |base: u16, x: u8, y: u8| base | ((x as u16 & 0xF) << 8) | ((y as u16 & 0xF) << 4);
+        let xkk = |base: u16, x: u8, kk: u8| base | ((x as u16 & 0xF) << 8) | kk as u16;
+        let fx = |low: u16, x: u8| 0xF000 | ((x as u16 & 0xF) << 8) | low;
+        match *self {
+            Sys(nnn) => nnn & 0xFFF,
+            Cls => 0x00E0,
+            Ret => 0x00EE,
+            Scd(n) => 0x00C0 | (n as u16 & 0xF),
+            Scr => 0x00FB,
+            Scl => 0x00FC,
+            Exit => 0x00FD,
+            Low => 0x00FE,
+            High => 0x00FF,
+            Scu(n) => 0x00D0 | (n as u16 & 0xF),
+            Jp(nnn) => 0x1000 | (nnn & 0xFFF),
+            Call(nnn) => 0x2000 | (nnn & 0xFFF),
+            SeVxByte(x, kk) => xkk(0x3000, x, kk),
+            SneVxByte(x, kk) => xkk(0x4000, x, kk),
+            SeVxVy(x, y) => xy(0x5000, x, y),
+            Save(x, y) => xy(0x5002, x, y),
+            Load(x, y) => xy(0x5003, x, y),
+            LdVxByte(x, kk) => xkk(0x6000, x, kk),
+            AddVxByte(x, kk) => xkk(0x7000, x, kk),
+            LdVxVy(x, y) => xy(0x8000, x, y),
+            Or(x, y) => xy(0x8001, x, y),
+            And(x, y) => xy(0x8002, x, y),
+            Xor(x, y) => xy(0x8003, x, y),
+            AddVxVy(x, y) => xy(0x8004, x, y),
+            Sub(x, y) => xy(0x8005, x, y),
+            Shr(x, y) => xy(0x8006, x, y),
+            Subn(x, y) => xy(0x8007, x, y),
+            Shl(x, y) => xy(0x800E, x, y),
+            SneVxVy(x, y) => xy(0x9000, x, y),
+            LdI(nnn) => 0xA000 | (nnn & 0xFFF),
+            JpV0(nnn) => 0xB000 | (nnn & 0xFFF),
+            Rnd(x, kk) => xkk(0xC000, x, kk),
+            Drw { x, y, n } => xy(0xD000, x, y) | (n as u16 & 0xF),
+            Skp(x) => xkk(0xE000, x, 0x9E),
+            Sknp(x) => xkk(0xE000, x, 0xA1),
+            LdILong => 0xF000,
+            Plane(n) => fx(0x01, n),
+            Audio => 0xF002,
+            LdVxDt(x) => fx(0x07, x),
+            LdVxK(x) => fx(0x0A, x),
+            LdDtVx(x) => fx(0x15, x),
+            LdStVx(x) => fx(0x18, x),
+            AddIVx(x) => fx(0x1E, x),
+            LdFVx(x) => fx(0x29, x),
+            LdHfVx(x) => fx(0x30, x),
+            LdBVx(x) => fx(0x33, x),
+            Pitch(x) => fx(0x3A, x),
+            LdIVx(x) => fx(0x55, x),
+            LdVxI(x) => fx(0x65, x),
+            LdRVx(x) => fx(0x75, x),
+            LdVxR(x) => fx(0x85, x),
+        }
+    }
+
+    //oldest platform that defines the instruction
+    pub fn platform(&self) -> Platform {
+        use Instruction::*;
+        match self {
+            Scd(_) | Scr | Scl | Exit | Low | High | LdHfVx(_) | LdRVx(_) | LdVxR(_) => Platform::SuperChip,
+            Scu(_) | Save(..) | Load(..) | LdILong | Plane(_) | Audio | Pitch(_) => Platform::XoChip,
+            _ => Platform::Chip8
+        }
+    }
+
+    //whether a ROM for `platform` can run it. Sys never can, there's no 1802 to run machine code on
+    pub fn is_supported_on(&self, platform: Platform) -> bool {
+        !matches!(self, Instruction::Sys(_)) && self.platform() <= platform
+    }
+
+    //bytes taken in memory including the address word of F000 nnnn
+    pub fn length(&self) -> usize {
+        match self {
+            Instruction::LdILong => 4,
+            _ => 2
+        }
+    }
+
+    //3xkk, 4xkk, 5xy0, 9xy0, Ex9E and ExA1
+    pub fn is_skip(&self) -> bool {
+        use Instruction::*;
+        matches!(self, SeVxByte(..) | SneVxByte(..) | SeVxVy(..) | SneVxVy(..) | Skp(_) | Sknp(_))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Instruction::*;
+
+    #[test]
+    fn every_decoded_opcode_encodes_back() {
+        for opcode in 0..=0xFFFF {
+            if let Some(instruction) = decode(opcode) {
+                assert_eq!(instruction.encode(), opcode, "{:04X} decoded to {:?}", opcode, instruction);
+            }
+        }
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let samples = [
+            Sys(0x123), Cls, Ret, Scd(7), Scr, Scl, Exit, Low, High, Scu(3), Jp(0x2A4), Call(0xFFF), SeVxByte(3, 0x1F),
+            SneVxByte(0xA, 0), SeVxVy(1, 2), Save(2, 5), Load(5, 2), LdVxByte(0xF, 0xFF), AddVxByte(0, 1), LdVxVy(4, 5),
+            Or(1, 1), And(2, 3), Xor(3, 4), AddVxVy(0xE, 0xF), Sub(5, 6), Shr(6, 7), Subn(7, 8), Shl(8, 9), SneVxVy(9, 0xA),
+            LdI(0x300), JpV0(0x400), Rnd(0xB, 0x0F), Drw { x: 0, y: 1, n: 5 }, Drw { x: 2, y: 3, n: 0 }, Skp(0xC), Sknp(0xD),
+            LdILong, Plane(3), Audio, LdVxDt(1), LdVxK(2), LdDtVx(3), LdStVx(4), AddIVx(5), LdFVx(6), LdHfVx(7), LdBVx(8),
+            Pitch(9), LdIVx(0xA), LdVxI(0xB), LdRVx(0xC), LdVxR(0xD),
+        ];
+        for instruction in samples {
+            assert_eq!(decode(instruction.encode()), Some(instruction));
+        }
+    }
+
+    #[test]
+    fn decodes_by_field() {
+        assert_eq!(decode(0x00E0), Some(Cls));
+        assert_eq!(decode(0x00EE), Some(Ret));
+        assert_eq!(decode(0x00C4), Some(Scd(4)));
+        assert_eq!(decode(0x00D2), Some(Scu(2)));
+        assert_eq!(decode(0x0123), Some(Sys(0x123)));
+        assert_eq!(decode(0x1ABC), Some(Jp(0xABC)));
+        assert_eq!(decode(0x2ABC), Some(Call(0xABC)));
+        assert_eq!(decode(0x631F), Some(LdVxByte(3, 0x1F)));
+        assert_eq!(decode(0x8AB4), Some(AddVxVy(0xA, 0xB)));
+        assert_eq!(decode(0xD015), Some(Drw { x: 0, y: 1, n: 5 }));
+        assert_eq!(decode(0xE59E), Some(Skp(5)));
+        assert_eq!(decode(0xE5A1), Some(Sknp(5)));
+        assert_eq!(decode(0xF000), Some(LdILong));
+        assert_eq!(decode(0xF201), Some(Plane(2)));
+        assert_eq!(decode(0xF733), Some(LdBVx(7)));
+        assert_eq!(decode(0xFF65), Some(LdVxI(0xF)));
+    }
+
+    #[test]
+    fn invalid_opcodes_do_not_decode() {
+        let invalid = [
+            0x5001, 0x5004, 0x500F, 0x8008, 0x800D, 0x800F, 0x9001, 0x900E, 0xE000, 0xE09F, 0xE0A2, 0xE19D,
+            0xF100, 0xF102, 0xF003, 0xF0FF, 0xF020, 0xF056, 0xF066, 0xF086, 0xF03B,
+        ];
+        for opcode in invalid {
+            assert_eq!(decode(opcode), None, "{:04X}", opcode);
+        }
+    }
+
+    #[test]
+    fn decodable_opcode_count() {
+        //0x0nnn: all 4096 (Sys or a named 00xx), 1-4, 6, 7, A-D: 4096 each, 5xyn: 3 of 16, 8xyn: 9 of 16,
+        //9xyn: 1 of 16, Exnn: 2 of 256 per x, Fxnn: 14 of 256 per x plus F000 and F002 for x = 0
+        let expected = 4096 + 4096 * 10 + 256 * 3 + 256 * 9 + 256 + 16 * 2 + 16 * 14 + 2;
+        assert_eq!((0..=0xFFFF).filter_map(decode).count(), expected);
+    }
+
+    #[test]
+    fn platform_gating() {
+        assert!(Cls.is_supported_on(Platform::Chip8));
+        assert!(!Sys(0x123).is_supported_on(Platform::XoChip));
+        assert!(!Scd(1).is_supported_on(Platform::Chip8));
+        assert!(Scd(1).is_supported_on(Platform::SuperChip));
+        assert!(LdHfVx(0).is_supported_on(Platform::XoChip));
+        assert!(!Plane(1).is_supported_on(Platform::SuperChip));
+        assert!(LdILong.is_supported_on(Platform::XoChip));
+        assert_eq!(LdILong.length(), 4);
+        assert_eq!(Cls.length(), 2);
+    }
+}
@@ -0,0 +1,36 @@
+//CHIP-8 / SUPER-CHIP / XO-CHIP emulator core. No windowing, audio or input code lives here,
+//frontends drive the Processor and read its framebuffer.
+
+pub mod asm;
+pub mod bus;
+mod chip8;
+pub mod debug;
+pub mod disasm;
+pub mod dump;
+mod error;
+mod fontset;
+mod framebuffer;
+pub mod headless;
+mod instruction;
+pub mod movie;
+mod quirks;
+mod rewind;
+mod rng;
+mod sha1;
+mod state;
+pub mod trace;
+
+pub use asm::{assemble, AsmError};
+pub use bus::Bus;
+pub use chip8::{Platform, Processor};
+pub use debug::Debugger;
+pub use error::Chip8Error;
+pub use fontset::{BIG_FONTSET, FONTSET};
+pub use framebuffer::{Framebuffer, HIRES_HEIGHT, HIRES_WIDTH, LORES_HEIGHT, LORES_WIDTH};
+pub use instruction::{decode, Instruction};
+pub use movie::{Movie, MovieError};
+pub use quirks::Quirks;
+pub use rewind::RewindBuffer;
+pub use rng::{Rng, RngMode};
+pub use sha1::sha1_hex;
+pub use state::{StateError, STATE_VERSION};
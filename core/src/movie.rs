@@ -0,0 +1,265 @@
+//Input movies. A movie holds the state a run started from and the keypad for every frame after it,
+//so replaying it on the same ROM repeats the run exactly: the start state carries the quirks,
+//platform and random number generator, and nothing else feeds into the emulation. A hash of the
+//state after the last frame lets a replay check that it ended where the recording did.
+
+use std::fmt;
+use crate::chip8::Processor;
+use crate::sha1::sha1_hex;
+use crate::state::{StateError, StateReader, StateWriter};
+
+pub const MOVIE_MAGIC: &[u8; 4] = b"C8MV";
+pub const MOVIE_VERSION: u16 = 1;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MovieError {
+    //not a chip8rs movie
+    BadMagic,
+    //written by a newer chip8rs
+    UnsupportedVersion(u16),
+    Truncated,
+    Invalid(&'static str),
+    //the movie was recorded on another ROM
+    WrongRom { expected: String, found: String },
+    //the start state didn't load
+    State(StateError),
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MovieError::BadMagic => write!(f, "not a chip8rs movie"),
+            MovieError::UnsupportedVersion(version) => write!(f, "unsupported movie version {}", version),
+            MovieError::Truncated => write!(f, "movie is truncated"),
+            MovieError::Invalid(field) => write!(f, "movie has an invalid {}", field),
+            MovieError::WrongRom { expected, found } => write!(f, "movie was recorded on ROM {} but this is {}", expected, found),
+            MovieError::State(error) => write!(f, "movie start state: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for MovieError {}
+
+//header errors come from the shared reader, which words them for save states
+impl From<StateError> for MovieError {
+    fn from(error: StateError) -> MovieError {
+        match error {
+            StateError::BadMagic => MovieError::BadMagic,
+            StateError::UnsupportedVersion(version) => MovieError::UnsupportedVersion(version),
+            StateError::Truncated => MovieError::Truncated,
+            StateError::Invalid(field) => MovieError::Invalid(field),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Movie {
+    //SHA-1 of the ROM, as sha1_hex prints it
+    rom_hash: String,
+    //the seed the recording was started with, kept so a bug report can name it
+    seed: u64,
+    instructions_per_frame: u32,
+    start_state: Vec<u8>,
+    //keypad for each frame, bit n set while key n is held
+    frames: Vec<u16>,
+    //SHA-1 of the save state after the last frame, set by finish
+    end_hash: Option<String>,
+}
+
+impl Movie {
+    //starts a recording from the processor as it is now
+    pub fn new(chip: &Processor, rom_hash: &str, seed: u64, instructions_per_frame: u32) -> Movie {
+        Movie { rom_hash: rom_hash.to_string(), seed, instructions_per_frame, start_state: chip.save_state(),
+            frames: Vec::new(), end_hash: None }
+    }
+
+    pub fn get_rom_hash(&self) -> &str {
+        &self.rom_hash
+    }
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+    pub fn get_instructions_per_frame(&self) -> u32 {
+        self.instructions_per_frame
+    }
+    //frames recorded
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    //call with the keypad just before each frame runs
+    pub fn record(&mut self, keypad: [u8; 16]) {
+        let keys = keypad.iter().enumerate().fold(0, |keys, (key, pressed)| keys | ((*pressed != 0) as u16) << key);
+        self.frames.push(keys);
+        self.end_hash = None;
+    }
+
+    //call once the last recorded frame has run
+    pub fn finish(&mut self, chip: &Processor) {
+        self.end_hash = Some(sha1_hex(&chip.save_state()));
+    }
+
+    //puts the processor back where the recording started. `rom_hash` is the ROM being played
+    pub fn start(&self, chip: &mut Processor, rom_hash: &str) -> Result<(), MovieError> {
+        if rom_hash != self.rom_hash {
+            return Err(MovieError::WrongRom { expected: self.rom_hash.clone(), found: rom_hash.to_string() });
+        }
+        chip.load_state(&self.start_state).map_err(MovieError::State)
+    }
+
+    //which keys are held in `frame`, counted from the start
+    pub fn get_keys(&self, frame: u64) -> Option<[bool; 16]> {
+        let keys = self.frames.get(frame as usize)?;
+        Some(std::array::from_fn(|key| keys & (1 << key) != 0))
+    }
+
+    //sets the keypad for `frame`. false once the movie has run out
+    pub fn apply(&self, chip: &mut Processor, frame: u64) -> bool {
+        let Some(keys) = self.get_keys(frame) else {
+            return false;
+        };
+        for (key, pressed) in keys.into_iter().enumerate() {
+            chip.set_key(key, pressed);
+        }
+        true
+    }
+
+    //after replaying every frame: Some(true) if the processor ended where the recording did, None
+    //for a movie that was never finished
+    pub fn verify(&self, chip: &Processor) -> Option<bool> {
+        self.end_hash.as_ref().map(|hash| *hash == sha1_hex(&chip.save_state()))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = StateWriter::with_header(MOVIE_MAGIC, MOVIE_VERSION);
+        writer.bytes(self.rom_hash.as_bytes());
+        writer.u64(self.seed);
+        writer.u32(self.instructions_per_frame);
+        writer.u32(self.start_state.len() as u32);
+        writer.bytes(&self.start_state);
+        writer.bool(self.end_hash.is_some());
+        writer.bytes(self.end_hash.as_deref().unwrap_or(&"0".repeat(40)).as_bytes());
+        writer.u32(self.frames.len() as u32);
+        for keys in &self.frames {
+            writer.u16(*keys);
+        }
+        writer.finish()
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Movie, MovieError> {
+        let (mut reader, _) = StateReader::with_header(data, MOVIE_MAGIC, MOVIE_VERSION)?;
+        let rom_hash = read_hash(&mut reader)?;
+        let seed = reader.u64()?;
+        let instructions_per_frame = reader.u32()?;
+        if instructions_per_frame == 0 {
+            return Err(MovieError::Invalid("instructions per frame"));
+        }
+        let state_len = reader.u32()? as usize;
+        let start_state = reader.bytes(state_len)?.to_vec();
+        let has_end = reader.bool()?;
+        let end_hash = read_hash(&mut reader)?;
+        let frame_count = reader.u32()?;
+        let frames = (0..frame_count).map(|_| reader.u16()).collect::<Result<Vec<u16>, StateError>>()?;
+        if !reader.is_empty() {
+            return Err(MovieError::Invalid("length"));
+        }
+        Ok(Movie { rom_hash, seed, instructions_per_frame, start_state, frames, end_hash: has_end.then_some(end_hash) })
+    }
+}
+
+//SHA-1s are stored as their 40 hex digits
+fn read_hash(reader: &mut StateReader) -> Result<String, MovieError> {
+    let hash = std::str::from_utf8(reader.bytes(40)?).ok().filter(|hash| hash.bytes().all(|byte| byte.is_ascii_hexdigit()));
+    Ok(hash.ok_or(MovieError::Invalid("hash"))?.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::chip8::Platform;
+    use crate::fontset::FONTSET;
+    use crate::rng::{Rng, RngMode};
+
+    //draws a random digit at a random spot while key 5 is held
+    const ROM: &str = "
+        loop:
+            RND V1, 0x3F
+            RND V2, 0x1F
+            RND V3, 0x0F
+            LD F, V3
+            LD V4, 5
+            SKNP V4
+            DRW V1, V2, 5
+            JP loop
+    ";
+
+    fn boot() -> (Processor, String) {
+        let rom = assemble(ROM).unwrap();
+        let mut chip = Processor::new(FONTSET, Platform::XoChip.default_quirks(), Platform::XoChip);
+        chip.load_rom(&rom).unwrap();
+        chip.set_rng(Rng::new(RngMode::SplitMix, 99));
+        (chip, sha1_hex(&rom))
+    }
+
+    fn recorded() -> (Movie, Processor) {
+        let (mut chip, rom_hash) = boot();
+        let mut movie = Movie::new(&chip, &rom_hash, 99, 10);
+        for frame in 0..120 {
+            chip.set_key(5, (10..40).contains(&frame) || frame % 7 == 0);
+            movie.record(chip.get_keypad());
+            chip.run_frame(10).unwrap();
+        }
+        movie.finish(&chip);
+        (movie, chip)
+    }
+
+    fn replay(movie: &Movie, chip: &mut Processor) {
+        let mut frame = 0;
+        while movie.apply(chip, frame) {
+            chip.run_frame(movie.get_instructions_per_frame()).unwrap();
+            frame += 1;
+        }
+    }
+
+    #[test]
+    fn replay_ends_where_the_recording_did() {
+        let (movie, recorded_chip) = recorded();
+        let movie = Movie::from_bytes(&movie.to_bytes()).unwrap();
+        assert_eq!((movie.len(), movie.get_seed()), (120, 99));
+        //a processor in some other state with another seed still replays exactly
+        let (mut chip, rom_hash) = boot();
+        chip.set_rng(Rng::new(RngMode::Vip, 1));
+        chip.run_frame(50).unwrap();
+        movie.start(&mut chip, &rom_hash).unwrap();
+        replay(&movie, &mut chip);
+        assert_eq!(movie.verify(&chip), Some(true));
+        assert_eq!(chip.get_vmemory(), recorded_chip.get_vmemory());
+    }
+
+    #[test]
+    fn desync_is_detected() {
+        let (movie, _) = recorded();
+        let (mut chip, rom_hash) = boot();
+        movie.start(&mut chip, &rom_hash).unwrap();
+        chip.set_rng(Rng::new(RngMode::SplitMix, 100));
+        replay(&movie, &mut chip);
+        assert_eq!(movie.verify(&chip), Some(false));
+    }
+
+    #[test]
+    fn rejects_other_roms_and_bad_files() {
+        let (movie, _) = recorded();
+        let (mut chip, _) = boot();
+        assert!(matches!(movie.start(&mut chip, &sha1_hex(b"other")), Err(MovieError::WrongRom { .. })));
+        let data = movie.to_bytes();
+        assert_eq!(Movie::from_bytes(&data[..data.len() - 1]), Err(MovieError::Truncated));
+        assert_eq!(Movie::from_bytes(&chip.save_state()), Err(MovieError::BadMagic));
+        let mut unfinished = movie.clone();
+        unfinished.record([0x0; 16]);
+        assert_eq!(Movie::from_bytes(&unfinished.to_bytes()).unwrap().verify(&chip), None);
+    }
+}
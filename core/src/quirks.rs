@@ -0,0 +1,69 @@
+//Behaviour toggles for the opcodes that different CHIP-8 interpreters disagree on.
+//Each preset matches the interpreter a ROM was most likely written for.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    //8xy6/8xyE shift Ry and store the result in Rx (VIP) instead of shifting Rx in place
+    pub shift_uses_vy: bool,
+    //Fx55/Fx65 leave index pointing past the last register copied (VIP)
+    pub load_store_increments_i: bool,
+    //Bnnn jumps to xnn + Rx (CHIP-48/SCHIP) instead of nnn + R0
+    pub jump_uses_vx: bool,
+    //8xy1/8xy2/8xy3 reset 0xF to 0 (VIP)
+    pub logic_resets_vf: bool,
+    //DXYN wraps sprites around the screen edges instead of clipping them
+    pub wrap_sprites: bool,
+    //DXYN waits for the next vblank, limiting sprite draws to one per frame (VIP)
+    pub display_wait: bool,
+}
+
+impl Quirks {
+    //original COSMAC VIP interpreter
+    pub const fn vip() -> Quirks {
+        Quirks { shift_uses_vy: true, load_store_increments_i: true, jump_uses_vx: false,
+            logic_resets_vf: true, wrap_sprites: false, display_wait: true }
+    }
+    //HP48 CHIP-48
+    pub const fn chip48() -> Quirks {
+        Quirks { shift_uses_vy: false, load_store_increments_i: true, jump_uses_vx: true,
+            logic_resets_vf: false, wrap_sprites: false, display_wait: false }
+    }
+    //SUPER-CHIP 1.1
+    pub const fn schip() -> Quirks {
+        Quirks { shift_uses_vy: false, load_store_increments_i: false, jump_uses_vx: true,
+            logic_resets_vf: false, wrap_sprites: false, display_wait: false }
+    }
+    //Octo / XO-CHIP, what most modern ROMs expect
+    pub const fn modern() -> Quirks {
+        Quirks { shift_uses_vy: true, load_store_increments_i: true, jump_uses_vx: false,
+            logic_resets_vf: false, wrap_sprites: true, display_wait: false }
+    }
+
+    //packed into one byte for save states
+    pub(crate) fn to_bits(self) -> u8 {
+        [self.shift_uses_vy, self.load_store_increments_i, self.jump_uses_vx, self.logic_resets_vf, self.wrap_sprites, self.display_wait]
+            .iter().enumerate()
+            .fold(0, |bits, (bit, set)| bits | ((*set as u8) << bit))
+    }
+    pub(crate) fn from_bits(bits: u8) -> Quirks {
+        let bit = |n: u8| bits & (1 << n) != 0;
+        Quirks { shift_uses_vy: bit(0), load_store_increments_i: bit(1), jump_uses_vx: bit(2),
+            logic_resets_vf: bit(3), wrap_sprites: bit(4), display_wait: bit(5) }
+    }
+
+    pub fn from_preset(name: &str) -> Option<Quirks> {
+        match name.to_lowercase().as_str() {
+            "vip" | "cosmac" | "chip8" | "chip-8" => Some(Quirks::vip()),
+            "chip48" | "chip-48" => Some(Quirks::chip48()),
+            "schip" | "superchip" | "schip1.1" => Some(Quirks::schip()),
+            "modern" | "octo" | "xochip" | "xo-chip" => Some(Quirks::modern()),
+            _ => None
+        }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Quirks {
+        Quirks::modern()
+    }
+}
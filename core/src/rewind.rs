@@ -0,0 +1,237 @@
+//Ring buffer of save states for rewinding. Only the newest snapshot is kept whole; every older one
+//is stored as the XOR against the snapshot after it, run-length encoded. Consecutive frames only
+//differ in a handful of memory and framebuffer bytes, so most deltas are a few dozen bytes.
+
+use std::collections::VecDeque;
+use crate::chip8::Processor;
+use crate::state::StateError;
+
+const DELTA: u8 = 0;
+//resolution changes alter the state length so those snapshots are stored whole
+const FULL: u8 = 1;
+
+pub struct RewindBuffer {
+    capacity: usize,
+    interval: u32,
+    frames_since_record: u32,
+    newest: Option<Vec<u8>>,
+    //back() turns `newest` into the snapshot before it, front() is the oldest
+    deltas: VecDeque<Vec<u8>>,
+}
+
+impl RewindBuffer {
+    //keeps `seconds` of 60hz frames, recording a snapshot every `interval` frames
+    pub fn new(seconds: u32, interval: u32) -> RewindBuffer {
+        let interval = interval.max(1);
+        RewindBuffer { capacity: (seconds as usize).saturating_mul(60) / interval as usize, interval, frames_since_record: 0, newest: None, deltas: VecDeque::new() }
+    }
+
+    //call once per emulated frame
+    pub fn record(&mut self, chip: &Processor) {
+        self.frames_since_record += 1;
+        if self.capacity == 0 || self.frames_since_record < self.interval {
+            return;
+        }
+        self.frames_since_record = 0;
+
+        let snapshot = chip.save_state();
+        if let Some(previous) = self.newest.take() {
+            self.deltas.push_back(encode(&snapshot, &previous));
+            if self.deltas.len() > self.capacity {
+                self.deltas.pop_front();
+            }
+        }
+        self.newest = Some(snapshot);
+    }
+
+    //steps the processor back one snapshot, false once there's nothing older left. a snapshot that
+    //won't load stays in the buffer and leaves the processor as it was
+    pub fn rewind(&mut self, chip: &mut Processor) -> Result<bool, StateError> {
+        let (Some(newest), Some(delta)) = (self.newest.as_ref(), self.deltas.back()) else {
+            return Ok(false);
+        };
+        let previous = decode(newest, delta);
+        chip.load_state(&previous)?;
+        self.deltas.pop_back();
+        self.newest = Some(previous);
+        self.frames_since_record = 0;
+        Ok(true)
+    }
+
+    pub fn len(&self) -> usize {
+        self.deltas.len() + self.newest.is_some() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.newest.is_none()
+    }
+
+    //bytes held by snapshots and deltas
+    pub fn memory_usage(&self) -> usize {
+        self.newest.as_ref().map_or(0, Vec::len) + self.deltas.iter().map(Vec::len).sum::<usize>()
+    }
+
+    pub fn clear(&mut self) {
+        self.newest = None;
+        self.deltas.clear();
+        self.frames_since_record = 0;
+    }
+}
+
+//delta that turns `from` back into `to`: runs of (unchanged count, changed count, xor bytes)
+fn encode(from: &[u8], to: &[u8]) -> Vec<u8> {
+    if from.len() != to.len() {
+        let mut out = vec![FULL];
+        out.extend_from_slice(to);
+        return out;
+    }
+    let mut out = vec![DELTA];
+    let mut idx = 0;
+    while idx < from.len() {
+        let same_start = idx;
+        while idx < from.len() && from[idx] == to[idx] {
+            idx += 1;
+        }
+        let diff_start = idx;
+        while idx < from.len() && from[idx] != to[idx] {
+            idx += 1;
+        }
+        write_varint(&mut out, diff_start - same_start);
+        write_varint(&mut out, idx - diff_start);
+        out.extend(from[diff_start..idx].iter().zip(&to[diff_start..idx]).map(|(a, b)| a ^ b));
+    }
+    out
+}
+
+fn decode(from: &[u8], delta: &[u8]) -> Vec<u8> {
+    if delta[0] == FULL {
+        return delta[1..].to_vec();
+    }
+    let mut out = from.to_vec();
+    let (mut position, mut idx) = (1, 0);
+    while position < delta.len() {
+        idx += read_varint(delta, &mut position);
+        let changed = read_varint(delta, &mut position);
+        for byte in &mut out[idx..idx + changed] {
+            *byte ^= delta[position];
+            position += 1;
+        }
+        idx += changed;
+    }
+    out
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(data: &[u8], position: &mut usize) -> usize {
+    let (mut value, mut shift) = (0, 0);
+    loop {
+        let byte = data[*position];
+        *position += 1;
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::chip8::Platform;
+    use crate::fontset::FONTSET;
+
+    fn round_trip(from: &[u8], to: &[u8]) -> Vec<u8> {
+        let delta = encode(from, to);
+        assert_eq!(decode(from, &delta), to);
+        delta
+    }
+
+    #[test]
+    fn huge_durations_saturate() {
+        assert_eq!(RewindBuffer::new(u32::MAX, 1).capacity, u32::MAX as usize * 60);
+        assert_eq!(RewindBuffer::new(10, 0).capacity, 600);
+        assert_eq!(RewindBuffer::new(10, 4).capacity, 150);
+    }
+
+    #[test]
+    fn deltas_round_trip() {
+        let state: Vec<u8> = (0..5000).map(|idx| (idx * 7) as u8).collect();
+        assert_eq!(round_trip(&state, &state), [DELTA, 0x88, 0x27, 0], "identical states are one unchanged run");
+        assert_eq!(round_trip(&[], &[]), [DELTA]);
+
+        let flipped: Vec<u8> = state.iter().map(|byte| !byte).collect();
+        let delta = round_trip(&state, &flipped);
+        assert_eq!(&delta[..4], &[DELTA, 0, 0x88, 0x27]);
+        assert!(delta[4..].iter().all(|byte| *byte == 0xFF));
+
+        //run lengths either side of the one and two byte varint limits
+        for run in [1, 127, 128, 129, 16383, 16384] {
+            let before = vec![0u8; run * 2 + 1];
+            let mut after = before.clone();
+            after[run] = 1;
+            after[run * 2] = 2;
+            round_trip(&before, &after);
+            after[..run].fill(3);
+            round_trip(&before, &after);
+        }
+
+        let delta = round_trip(&state, &state[..100]);
+        assert_eq!((delta[0], delta.len()), (FULL, 101), "a length change stores the state whole");
+    }
+
+    #[test]
+    fn varints() {
+        for value in [0, 1, 127, 128, 300, 16383, 16384, usize::MAX >> 1] {
+            let mut out = Vec::new();
+            write_varint(&mut out, value);
+            let mut position = 0;
+            assert_eq!(read_varint(&out, &mut position), value);
+            assert_eq!(position, out.len());
+        }
+    }
+
+    #[test]
+    fn rewinds_frame_by_frame() {
+        let mut chip = Processor::new(FONTSET, Platform::Chip8.default_quirks(), Platform::Chip8);
+        chip.load_rom(&assemble("
+            loop:
+                ADD V0, 1
+                JP loop
+        ").unwrap()).unwrap();
+        let mut rewind = RewindBuffer::new(1, 1);
+        let mut states = Vec::new();
+        for _ in 0..5 {
+            chip.run_frame(2).unwrap();
+            rewind.record(&chip);
+            states.push(chip.save_state());
+        }
+        assert_eq!(rewind.len(), 5);
+        for state in states.iter().rev().skip(1) {
+            assert_eq!(rewind.rewind(&mut chip), Ok(true));
+            assert_eq!(&chip.save_state(), state);
+        }
+        assert_eq!(rewind.rewind(&mut chip), Ok(false));
+    }
+
+    #[test]
+    fn unloadable_snapshots_are_kept() {
+        let mut chip = Processor::new(FONTSET, Platform::Chip8.default_quirks(), Platform::Chip8);
+        let mut rewind = RewindBuffer::new(1, 1);
+        rewind.record(&chip);
+        rewind.record(&chip);
+        let state = chip.save_state();
+        //an older snapshot that isn't a save state
+        *rewind.deltas.back_mut().unwrap() = [FULL].iter().chain(b"NOPE").copied().collect();
+        assert_eq!(rewind.rewind(&mut chip), Err(StateError::BadMagic));
+        assert_eq!((chip.save_state(), rewind.len()), (state, 2));
+    }
+}
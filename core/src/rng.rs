@@ -0,0 +1,160 @@
+//Random numbers for Cxkk. The Processor owns one so runs, replays and save states are reproducible
+//from a seed.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RngMode {
+    //SplitMix64, well distributed and any seed (including 0) works
+    SplitMix,
+    //the COSMAC VIP interpreter's Cxkk routine at 0x01D9, seeded through its 16 bit R9
+    Vip,
+}
+
+impl RngMode {
+    pub fn from_name(name: &str) -> Option<RngMode> {
+        match name.to_lowercase().as_str() {
+            "splitmix" | "default" => Some(RngMode::SplitMix),
+            "vip" | "cosmac" => Some(RngMode::Vip),
+            _ => None
+        }
+    }
+}
+
+//0x0100-0x01FF of the VIP's CHIP-8 interpreter, the page its Cxkk routine reads as a table. from 0x01F2
+//on it is unused padding
+const VIP_PAGE: [u8; 256] = [
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x45, 0xA3, 0x98, 0x56, 0xD4, 0xF8, 0x81, 0xBC, 0xF8, 0x95, 0xAC,
+    0x22, 0xDC, 0x12, 0x56, 0xD4, 0x06, 0xB8, 0xD4, 0x06, 0xA8, 0xD4, 0x64, 0x0A, 0x01, 0xE6, 0x8A,
+    0xF4, 0xAA, 0x3B, 0x28, 0x9A, 0xFC, 0x01, 0xBA, 0xD4, 0xF8, 0x81, 0xBA, 0x06, 0xFA, 0x0F, 0xAA,
+    0x0A, 0xAA, 0xD4, 0xE6, 0x06, 0xBF, 0x93, 0xBE, 0xF8, 0x1B, 0xAE, 0x2A, 0x1A, 0xF8, 0x00, 0x5A,
+    0x0E, 0xF5, 0x3B, 0x4B, 0x56, 0x0A, 0xFC, 0x01, 0x5A, 0x30, 0x40, 0x4E, 0xF6, 0x3B, 0x3C, 0x9F,
+    0x56, 0x2A, 0x2A, 0xD4, 0x00, 0x22, 0x86, 0x52, 0xF8, 0xF0, 0xA7, 0x07, 0x5A, 0x87, 0xF3, 0x17,
+    0x1A, 0x3A, 0x5B, 0x12, 0xD4, 0x22, 0x86, 0x52, 0xF8, 0xF0, 0xA7, 0x0A, 0x57, 0x87, 0xF3, 0x17,
+    0x1A, 0x3A, 0x6B, 0x12, 0xD4, 0x15, 0x85, 0x22, 0x73, 0x95, 0x52, 0x25, 0x45, 0xA5, 0x86, 0xFA,
+    0x0F, 0xB5, 0xD4, 0x45, 0xE6, 0xF3, 0x3A, 0x82, 0x15, 0x15, 0xD4, 0x45, 0xE6, 0xF3, 0x3A, 0x88,
+    0xD4, 0x45, 0x07, 0x30, 0x8C, 0x45, 0x07, 0x30, 0x84, 0xE6, 0x62, 0x26, 0x45, 0xA3, 0x36, 0x88,
+    0xD4, 0x3E, 0x88, 0xD4, 0xF8, 0xF0, 0xA7, 0xE7, 0x45, 0xF4, 0xA5, 0x86, 0xFA, 0x0F, 0x3B, 0xB2,
+    0xFC, 0x01, 0xB5, 0xD4, 0x45, 0x56, 0xD4, 0x45, 0xE6, 0xF4, 0x56, 0xD4, 0x45, 0xFA, 0x0F, 0x3A,
+    0xC4, 0x07, 0x56, 0xD4, 0xAF, 0x22, 0xF8, 0xD3, 0x73, 0x8F, 0xF9, 0xF0, 0x52, 0xE6, 0x07, 0xD2,
+    0x56, 0xF8, 0xFF, 0xA6, 0xF8, 0x00, 0x7E, 0x56, 0xD4, 0x19, 0x89, 0xAE, 0x93, 0xBE, 0x99, 0xEE,
+    0xF4, 0x56, 0x76, 0xE6, 0xF4, 0xB9, 0x56, 0x45, 0xF2, 0x56, 0xD4, 0x45, 0xAA, 0x86, 0xFA, 0x0F,
+    0xBA, 0xD4, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE0, 0x00, 0x4B,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rng {
+    mode: RngMode,
+    //the SplitMix64 state, or R9 in the low 16 bits
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(mode: RngMode, seed: u64) -> Rng {
+        let state = match mode {
+            RngMode::SplitMix => seed,
+            RngMode::Vip => seed & 0xFFFF,
+        };
+        Rng { mode, state }
+    }
+
+    pub fn mode(&self) -> RngMode {
+        self.mode
+    }
+
+    pub(crate) fn next_byte(&mut self) -> u8 {
+        match self.mode {
+            RngMode::SplitMix => {
+                self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+                let mut z = self.state;
+                z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+                (z ^ (z >> 31)) as u8
+            }
+            RngMode::Vip => {
+                //INC R9, then R9.1 + M(0x0100 + R9.0) with carry, a ring shift right (SHRC) of that
+                //added back onto it (ADD), and the result kept in R9.1. the caller does the AND with kk
+                self.state = (self.state + 1) & 0xFFFF;
+                let (high, low) = ((self.state >> 8) as u8, self.state as u8);
+                let (sum, carry) = high.overflowing_add(VIP_PAGE[low as usize]);
+                let value = sum.wrapping_add(sum >> 1 | (carry as u8) << 7);
+                self.state = (value as u64) << 8 | low as u64;
+                value
+            }
+        }
+    }
+
+    //for save states: a byte for the mode, then its state
+    pub(crate) fn to_parts(self) -> (u8, u64) {
+        (self.mode as u8, self.state)
+    }
+    pub(crate) fn from_parts(mode: u8, state: u64) -> Option<Rng> {
+        match (mode, state) {
+            (0, _) => Some(Rng { mode: RngMode::SplitMix, state }),
+            (1, 0..=0xFFFF) => Some(Rng { mode: RngMode::Vip, state }),
+            _ => None
+        }
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Rng {
+        Rng::new(RngMode::SplitMix, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(rng: &mut Rng, count: usize) -> Vec<u8> {
+        (0..count).map(|_| rng.next_byte()).collect()
+    }
+
+    #[test]
+    fn same_seed_same_sequence() {
+        for mode in [RngMode::SplitMix, RngMode::Vip] {
+            let first = bytes(&mut Rng::new(mode, 1234), 64);
+            assert_eq!(first, bytes(&mut Rng::new(mode, 1234), 64));
+            assert_ne!(first, bytes(&mut Rng::new(mode, 1235), 64));
+        }
+    }
+
+    #[test]
+    fn split_mix_covers_every_byte() {
+        let mut rng = Rng::default();
+        let mut seen = [false; 256];
+        for byte in bytes(&mut rng, 4096) {
+            seen[byte as usize] = true;
+        }
+        assert!(seen.iter().all(|seen| *seen));
+    }
+
+    #[test]
+    fn vip_routine() {
+        //the page holds the routine itself: INC R9, GLO R9, PLO RE, GHI R3, PHI RE, GHI R9, SEX RE, ADD
+        assert_eq!(VIP_PAGE[0xD9..0xE1], [0x19, 0x89, 0xAE, 0x93, 0xBE, 0x99, 0xEE, 0xF4]);
+        //R9 0x00D8 steps to 0x00D9, which reads 0x19: 0x19 + 0x0C
+        let mut rng = Rng::new(RngMode::Vip, 0x00D8);
+        assert_eq!(rng.next_byte(), 0x25);
+        assert_eq!(rng.to_parts(), (1, 0x25D9));
+        //0xF0 + 0x19 carries into the shift: 0x09 + 0x84
+        let mut rng = Rng::new(RngMode::Vip, 0xF0D8);
+        assert_eq!(rng.next_byte(), 0x8D);
+        assert_eq!(rng.to_parts(), (1, 0x8DD9));
+        //INC R9 carries into R9.1 before it's read
+        let mut rng = Rng::new(RngMode::Vip, 0x00FF);
+        assert_eq!(rng.next_byte(), 0x01);
+        assert_eq!(rng.to_parts(), (1, 0x0100));
+    }
+
+    #[test]
+    fn save_state_parts_round_trip() {
+        for mode in [RngMode::SplitMix, RngMode::Vip] {
+            let mut rng = Rng::new(mode, 77);
+            rng.next_byte();
+            let (kind, state) = rng.to_parts();
+            assert_eq!(Rng::from_parts(kind, state), Some(rng));
+        }
+        assert_eq!(Rng::from_parts(1, 0x10000), None);
+        assert_eq!(Rng::from_parts(2, 0), None);
+    }
+}
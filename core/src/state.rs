@@ -0,0 +1,126 @@
+//Versioned little-endian binary format used by Processor::save_state / load_state. Movies reuse the
+//writer and reader with their own header.
+
+use std::fmt;
+
+pub const STATE_MAGIC: &[u8; 4] = b"C8ST";
+//2 added the random number generator
+pub const STATE_VERSION: u16 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateError {
+    //not a chip8rs save state
+    BadMagic,
+    //written by a newer chip8rs
+    UnsupportedVersion(u16),
+    //ran out of bytes before the end of the state
+    Truncated,
+    //a field holds a value this Processor can't have
+    Invalid(&'static str),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::BadMagic => write!(f, "not a chip8rs save state"),
+            StateError::UnsupportedVersion(version) => write!(f, "unsupported save state version {}", version),
+            StateError::Truncated => write!(f, "save state is truncated"),
+            StateError::Invalid(field) => write!(f, "save state has an invalid {}", field),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+pub(crate) struct StateWriter {
+    buffer: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new() -> StateWriter {
+        StateWriter::with_header(STATE_MAGIC, STATE_VERSION)
+    }
+    pub fn with_header(magic: &[u8; 4], version: u16) -> StateWriter {
+        let mut buffer = magic.to_vec();
+        buffer.extend_from_slice(&version.to_le_bytes());
+        StateWriter { buffer }
+    }
+    pub fn u8(&mut self, value: u8) {
+        self.buffer.push(value);
+    }
+    pub fn bool(&mut self, value: bool) {
+        self.buffer.push(value as u8);
+    }
+    pub fn u16(&mut self, value: u16) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+    pub fn u32(&mut self, value: u32) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+    pub fn u64(&mut self, value: u64) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+    pub fn finish(self) -> Vec<u8> {
+        self.buffer
+    }
+}
+
+pub(crate) struct StateReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> StateReader<'a> {
+    //checks the header, returns the reader and the version the state was written with
+    pub fn new(data: &'a [u8]) -> Result<(StateReader<'a>, u16), StateError> {
+        StateReader::with_header(data, STATE_MAGIC, STATE_VERSION)
+    }
+    //same for another format, `version` being the newest one this build reads
+    pub fn with_header(data: &'a [u8], magic: &[u8; 4], version: u16) -> Result<(StateReader<'a>, u16), StateError> {
+        if data.len() < 6 || &data[..4] != magic {
+            return Err(StateError::BadMagic);
+        }
+        let newest = version;
+        let mut reader = StateReader { data, position: 4 };
+        let version = reader.u16()?;
+        if version > newest {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+        Ok((reader, version))
+    }
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        let bytes = self.data.get(self.position..self.position + len).ok_or(StateError::Truncated)?;
+        self.position += len;
+        Ok(bytes)
+    }
+    pub fn array<const N: usize>(&mut self) -> Result<[u8; N], StateError> {
+        let mut array = [0x0; N];
+        array.copy_from_slice(self.bytes(N)?);
+        Ok(array)
+    }
+    pub fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.bytes(1)?[0])
+    }
+    pub fn bool(&mut self) -> Result<bool, StateError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(StateError::Invalid("flag"))
+        }
+    }
+    pub fn u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+    pub fn u32(&mut self) -> Result<u32, StateError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+    pub fn u64(&mut self) -> Result<u64, StateError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+    pub fn is_empty(&self) -> bool {
+        self.position == self.data.len()
+    }
+}
@@ -0,0 +1,190 @@
+//Execution traces. While tracing is on Processor::cycle records a TraceEntry after every instruction;
+//a TraceWriter filters them and writes either one text line per instruction, meant for diffing
+//against other emulators' logs, or fixed size little-endian records for long runs.
+
+use std::io::{self, Write};
+use crate::disasm;
+
+pub const TRACE_MAGIC: &[u8; 4] = b"C8TR";
+pub const TRACE_VERSION: u16 = 1;
+//cycle u64, frame u32, pc, opcode, next word, V0-VF, I, SP u8
+const RECORD_SIZE: usize = 8 + 4 + 2 * 3 + 16 + 2 + 1;
+
+//one executed instruction with the registers as it left them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    //instructions executed before this one
+    pub cycle: u64,
+    //frame the instruction ran in, counting from 0
+    pub frame: u64,
+    pub pc: u16,
+    pub opcode: u16,
+    //the word after the opcode, only needed to disassemble XO-CHIP F000 nnnn
+    pub next_word: u16,
+    pub registers: [u8; 16],
+    pub index_register: u16,
+    pub stack_pointer: u16,
+}
+
+impl TraceEntry {
+    //"     42 f1     0x206  8127  SUB V1, V2          V 05 0A .. 00  I 0x300  SP 0"
+    pub fn to_line(&self) -> String {
+        let registers: Vec<String> = self.registers.iter().map(|value| format!("{:02X}", value)).collect();
+        format!("{:>10} f{:<6} 0x{:03X}  {:04X}  {:<20} V {}  I 0x{:03X}  SP {}", self.cycle, self.frame, self.pc, self.opcode,
+            disasm::mnemonic(self.opcode, self.next_word), registers.join(" "), self.index_register, self.stack_pointer)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceFormat {
+    Text,
+    Binary,
+}
+
+impl TraceFormat {
+    pub fn from_name(name: &str) -> Option<TraceFormat> {
+        match name.to_lowercase().as_str() {
+            "text" => Some(TraceFormat::Text),
+            "binary" | "bin" => Some(TraceFormat::Binary),
+            _ => None
+        }
+    }
+}
+
+//which entries get written, everything by default
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TraceFilter {
+    //inclusive range of instruction addresses
+    pub pc_range: Option<(u16, u16)>,
+    //skip everything before this frame
+    pub from_frame: u64,
+}
+
+impl TraceFilter {
+    pub fn matches(&self, entry: &TraceEntry) -> bool {
+        entry.frame >= self.from_frame && self.pc_range.is_none_or(|(start, end)| (start..=end).contains(&entry.pc))
+    }
+}
+
+pub struct TraceWriter<W: Write> {
+    writer: W,
+    format: TraceFormat,
+    filter: TraceFilter,
+}
+
+impl<W: Write> TraceWriter<W> {
+    //binary traces start with the magic and version straight away
+    pub fn new(mut writer: W, format: TraceFormat, filter: TraceFilter) -> io::Result<TraceWriter<W>> {
+        if format == TraceFormat::Binary {
+            writer.write_all(TRACE_MAGIC)?;
+            writer.write_all(&TRACE_VERSION.to_le_bytes())?;
+        }
+        Ok(TraceWriter { writer, format, filter })
+    }
+
+    pub fn write(&mut self, entries: &[TraceEntry]) -> io::Result<()> {
+        for entry in entries.iter().filter(|entry| self.filter.matches(entry)) {
+            match self.format {
+                TraceFormat::Text => writeln!(self.writer, "{}", entry.to_line())?,
+                TraceFormat::Binary => {
+                    let mut record = Vec::with_capacity(RECORD_SIZE);
+                    record.extend_from_slice(&entry.cycle.to_le_bytes());
+                    record.extend_from_slice(&(entry.frame as u32).to_le_bytes());
+                    for word in [entry.pc, entry.opcode, entry.next_word] {
+                        record.extend_from_slice(&word.to_le_bytes());
+                    }
+                    record.extend_from_slice(&entry.registers);
+                    record.extend_from_slice(&entry.index_register.to_le_bytes());
+                    record.push(entry.stack_pointer as u8);
+                    self.writer.write_all(&record)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+//entries of a binary trace, None if it isn't one or ends partway through a record
+pub fn read_binary(data: &[u8]) -> Option<Vec<TraceEntry>> {
+    let records = data.strip_prefix(TRACE_MAGIC)?.strip_prefix(&TRACE_VERSION.to_le_bytes()[..])?;
+    if records.len() % RECORD_SIZE != 0 {
+        return None;
+    }
+    let entries = records.chunks(RECORD_SIZE).map(|record| {
+        let word = |offset: usize| u16::from_le_bytes([record[offset], record[offset + 1]]);
+        let mut registers = [0x0; 16];
+        registers.copy_from_slice(&record[18..34]);
+        TraceEntry {
+            cycle: u64::from_le_bytes(record[..8].try_into().unwrap()),
+            frame: u32::from_le_bytes(record[8..12].try_into().unwrap()) as u64,
+            pc: word(12),
+            opcode: word(14),
+            next_word: word(16),
+            registers,
+            index_register: word(34),
+            stack_pointer: record[36] as u16,
+        }
+    }).collect();
+    Some(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::chip8::{Platform, Processor};
+    use crate::fontset::FONTSET;
+
+    fn traced(frames: usize) -> Vec<TraceEntry> {
+        let mut chip = Processor::new(FONTSET, Platform::Chip8.default_quirks(), Platform::Chip8);
+        chip.load_rom(&assemble("
+            loop:
+                ADD V0, 1
+                LD I, 0x300
+                JP loop
+        ").unwrap()).unwrap();
+        chip.set_tracing(true);
+        for _ in 0..frames {
+            chip.run_frame(3).unwrap();
+        }
+        chip.take_trace()
+    }
+
+    #[test]
+    fn records_registers_after_each_instruction() {
+        let entries = traced(2);
+        assert_eq!(entries.len(), 6);
+        assert_eq!((entries[0].cycle, entries[0].frame, entries[0].pc, entries[0].opcode), (0, 0, 0x200, 0x7001));
+        assert_eq!(entries[0].registers[0], 1);
+        assert_eq!(entries[1].index_register, 0x300);
+        assert_eq!((entries[5].cycle, entries[5].frame, entries[5].pc), (5, 1, 0x204));
+        assert_eq!(entries[3].registers[0], 2);
+    }
+
+    #[test]
+    fn text_lines_are_filtered() {
+        let mut writer = TraceWriter::new(Vec::new(), TraceFormat::Text, TraceFilter { pc_range: Some((0x202, 0x203)), from_frame: 1 }).unwrap();
+        writer.write(&traced(3)).unwrap();
+        let text = String::from_utf8(writer.writer).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("0x202  A300  LD I, 0x300"), "{}", lines[0]);
+        assert!(lines[0].trim_start().starts_with("4 f1"), "{}", lines[0]);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let entries = traced(2);
+        let mut writer = TraceWriter::new(Vec::new(), TraceFormat::Binary, TraceFilter::default()).unwrap();
+        writer.write(&entries).unwrap();
+        let data = writer.writer;
+        assert_eq!(data.len(), 6 + entries.len() * RECORD_SIZE);
+        assert_eq!(read_binary(&data), Some(entries));
+        assert_eq!(read_binary(&data[..data.len() - 1]), None);
+        assert_eq!(read_binary(b"C8ST"), None);
+    }
+}
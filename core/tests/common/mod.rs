@@ -0,0 +1,46 @@
+//Helpers shared by the integration tests. Every test builds its program with the in-tree assembler
+//and drives the Processor only through its public API.
+#![allow(dead_code)]
+
+use chip8rs_core::{assemble, dump, Platform, Processor, Quirks, FONTSET};
+
+pub fn boot_with(platform: Platform, quirks: Quirks, source: &str) -> Processor {
+    let mut chip = Processor::new(FONTSET, quirks, platform);
+    chip.load_rom(&assemble(source).unwrap_or_else(|error| panic!("{}", error))).unwrap();
+    chip
+}
+
+pub fn boot(platform: Platform, source: &str) -> Processor {
+    boot_with(platform, platform.default_quirks(), source)
+}
+
+//runs `steps` instructions, panicking on any fault
+pub fn step(chip: &mut Processor, steps: usize) {
+    for _ in 0..steps {
+        chip.cycle().unwrap_or_else(|error| panic!("{}", error));
+    }
+}
+
+pub fn run_with(platform: Platform, quirks: Quirks, source: &str, steps: usize) -> Processor {
+    let mut chip = boot_with(platform, quirks, source);
+    step(&mut chip, steps);
+    chip
+}
+
+pub fn run(platform: Platform, source: &str, steps: usize) -> Processor {
+    run_with(platform, platform.default_quirks(), source, steps)
+}
+
+pub fn register(chip: &Processor, index: usize) -> u8 {
+    chip.get_registers()[index]
+}
+
+//the framebuffer hash chip8-headless --output hash prints, what the ROM tests compare against
+pub fn screen_hash(chip: &Processor) -> String {
+    format!("{:016x}", dump::hash(chip.get_vmemory()))
+}
+
+//the framebuffer as text for assertion messages
+pub fn screen(chip: &Processor) -> String {
+    dump::ascii(chip.get_vmemory())
+}
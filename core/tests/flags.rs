@@ -0,0 +1,110 @@
+//VF edge cases: the flag is written after the result, so when VF is the destination it ends up
+//holding the flag, and when VF is the source the operation sees its value from before.
+
+mod common;
+
+use chip8rs_core::{Platform, Quirks};
+use common::{register, run, run_with};
+
+//runs `op` with VF = vf and V1 = v1 on SCHIP quirks, returns (V1, VF)
+fn flag_op(op: &str, v1: u8, vf: u8) -> (u8, u8) {
+    let source = format!("
+        LD V1, {}
+        LD VF, {}
+        {}
+    ", v1, vf, op);
+    let chip = run(Platform::SuperChip, &source, 3);
+    (register(&chip, 1), register(&chip, 0xF))
+}
+
+#[test]
+fn add_carry_boundaries() {
+    assert_eq!(flag_op("ADD V1, VF", 0x80, 0x7F), (0xFF, 0));
+    assert_eq!(flag_op("ADD V1, VF", 0x80, 0x80), (0x00, 1));
+    assert_eq!(flag_op("ADD V1, VF", 0xFF, 0x01), (0x00, 1));
+}
+
+#[test]
+fn sub_equal_values_do_not_borrow() {
+    assert_eq!(flag_op("SUB V1, VF", 5, 5), (0, 1));
+    assert_eq!(flag_op("SUB V1, VF", 5, 6), (0xFF, 0));
+    assert_eq!(flag_op("SUB V1, VF", 6, 5), (1, 1));
+}
+
+#[test]
+fn subn_equal_values_do_not_borrow() {
+    assert_eq!(flag_op("SUBN V1, VF", 5, 5), (0, 1));
+    assert_eq!(flag_op("SUBN V1, VF", 6, 5), (0xFF, 0));
+    assert_eq!(flag_op("SUBN V1, VF", 5, 6), (1, 1));
+}
+
+#[test]
+fn flag_wins_when_vf_is_the_destination() {
+    //the result would be 0x10 / 0x0F / 0x01 / 0x02 / 0x80, the flag overwrites it
+    assert_eq!(flag_op("ADD VF, V1", 0xF0, 0x20).1, 1);
+    assert_eq!(flag_op("ADD VF, V1", 0x01, 0x20).1, 0);
+    assert_eq!(flag_op("SUB VF, V1", 0x01, 0x10).1, 1);
+    assert_eq!(flag_op("SUB VF, V1", 0x10, 0x01).1, 0);
+    assert_eq!(flag_op("SUBN VF, V1", 0x10, 0x01).1, 1);
+    assert_eq!(flag_op("SUBN VF, V1", 0x01, 0x10).1, 0);
+    assert_eq!(flag_op("SHR VF", 0, 0x03).1, 1);
+    assert_eq!(flag_op("SHR VF", 0, 0x04).1, 0);
+    assert_eq!(flag_op("SHL VF", 0, 0x81).1, 1);
+    assert_eq!(flag_op("SHL VF", 0, 0x40).1, 0);
+}
+
+#[test]
+fn vf_as_source_is_read_before_the_flag() {
+    //0x10 - 0x20 borrows, the result uses the old VF
+    assert_eq!(flag_op("SUB V1, VF", 0x10, 0x20), (0xF0, 0));
+    assert_eq!(flag_op("SUBN V1, VF", 0x10, 0x20), (0x10, 1));
+    assert_eq!(flag_op("ADD V1, VF", 0x10, 0xF8), (0x08, 1));
+}
+
+#[test]
+fn shifts_from_vf_on_the_vip() {
+    let source = "
+        LD VF, 0x81
+        SHR V1, VF
+        LD V2, VF
+        LD VF, 0x81
+        SHL V3, VF
+        LD V4, VF
+    ";
+    let chip = run(Platform::Chip8, source, 6);
+    assert_eq!((register(&chip, 1), register(&chip, 2)), (0x40, 1));
+    assert_eq!((register(&chip, 3), register(&chip, 4)), (0x02, 1));
+}
+
+#[test]
+fn logic_reset_applies_after_the_result() {
+    let vip = Quirks::vip();
+    let chip = run_with(Platform::Chip8, vip, "
+        LD V1, 0x0F
+        LD VF, 0xF0
+        OR VF, V1
+    ", 3);
+    assert_eq!(register(&chip, 0xF), 0);
+    let chip = run_with(Platform::Chip8, Quirks { logic_resets_vf: false, ..vip }, "
+        LD V1, 0x0F
+        LD VF, 0xF0
+        OR VF, V1
+    ", 3);
+    assert_eq!(register(&chip, 0xF), 0xFF);
+}
+
+#[test]
+fn flag_ops_only_touch_vx_and_vf() {
+    let chip = run(Platform::SuperChip, "
+        LD V0, 0x11
+        LD V1, 0xFF
+        LD V2, 0x01
+        LD V3, 0x33
+        ADD V1, V2
+        SUB V1, V2
+        SUBN V1, V2
+        SHR V1
+        SHL V1
+    ", 9);
+    assert_eq!((register(&chip, 0), register(&chip, 2), register(&chip, 3)), (0x11, 0x01, 0x33));
+}
@@ -0,0 +1,707 @@
+//One test per opcode, run on the platform that introduced it with that platform's default quirks
+//unless the opcode's behaviour doesn't depend on them.
+
+mod common;
+
+use chip8rs_core::{Chip8Error, Platform, Processor, Rng, RngMode, FONTSET, BIG_FONTSET};
+use common::{boot, register, run, step};
+
+#[test]
+fn op_00e0_cls() {
+    let chip = run(Platform::Chip8, "
+            LD I, sprite
+            DRW V0, V0, 1
+            CLS
+            JP $
+        sprite:
+            DB 0xFF
+    ", 3);
+    assert!(chip.get_vmemory().pixels().iter().all(|pixel| *pixel == 0));
+}
+
+#[test]
+fn op_2nnn_00ee_call_and_ret() {
+    let mut chip = boot(Platform::Chip8, "
+            CALL sub
+            LD V1, 1
+            JP $
+        sub:
+            LD V0, 1
+            RET
+    ");
+    step(&mut chip, 1);
+    assert_eq!((chip.get_program_counter(), chip.get_stack()), (0x206, &[0x202][..]));
+    step(&mut chip, 3);
+    assert_eq!((chip.get_program_counter(), chip.get_stack_pointer()), (0x204, 0));
+    assert_eq!((register(&chip, 0), register(&chip, 1)), (1, 1));
+}
+
+#[test]
+fn op_2nnn_00ee_stack_faults() {
+    let mut chip = boot(Platform::Chip8, "RET");
+    assert_eq!(chip.cycle(), Err(Chip8Error::StackUnderflow { pc: 0x200, opcode: 0x00EE }));
+
+    let mut chip = boot(Platform::Chip8, "start: CALL start");
+    step(&mut chip, 16);
+    assert_eq!(chip.cycle(), Err(Chip8Error::StackOverflow { pc: 0x200, opcode: 0x2200 }));
+}
+
+#[test]
+fn op_00cn_scd() {
+    let chip = run(Platform::SuperChip, "
+            LD I, sprite
+            DRW V0, V0, 1
+            SCD 3
+            JP $
+        sprite:
+            DB 0x80
+    ", 3);
+    assert_eq!((chip.get_vmemory().get(0, 0), chip.get_vmemory().get(0, 3)), (0, 1));
+}
+
+#[test]
+fn op_00dn_scu() {
+    let chip = run(Platform::XoChip, "
+            LD V1, 5
+            LD I, sprite
+            DRW V0, V1, 1
+            SCU 2
+            JP $
+        sprite:
+            DB 0x80
+    ", 4);
+    assert_eq!((chip.get_vmemory().get(0, 5), chip.get_vmemory().get(0, 3)), (0, 1));
+}
+
+#[test]
+fn op_00fb_scr() {
+    let chip = run(Platform::SuperChip, "
+            LD I, sprite
+            DRW V0, V0, 1
+            SCR
+            JP $
+        sprite:
+            DB 0x80
+    ", 3);
+    assert_eq!((chip.get_vmemory().get(0, 0), chip.get_vmemory().get(4, 0)), (0, 1));
+}
+
+#[test]
+fn op_00fc_scl() {
+    let chip = run(Platform::SuperChip, "
+            LD V1, 6
+            LD I, sprite
+            DRW V1, V0, 1
+            SCL
+            JP $
+        sprite:
+            DB 0x80
+    ", 4);
+    assert_eq!((chip.get_vmemory().get(6, 0), chip.get_vmemory().get(2, 0)), (0, 1));
+}
+
+#[test]
+fn op_00fd_exit() {
+    let mut chip = boot(Platform::SuperChip, "EXIT");
+    step(&mut chip, 1);
+    assert!(chip.has_exited());
+    chip.run_frame(10).unwrap();
+    assert_eq!(chip.get_program_counter(), 0x200);
+}
+
+#[test]
+fn op_00fe_00ff_low_and_high() {
+    let mut chip = boot(Platform::SuperChip, "
+        HIGH
+        LOW
+    ");
+    step(&mut chip, 1);
+    assert_eq!((chip.get_vmemory().width(), chip.get_vmemory().height()), (128, 64));
+    step(&mut chip, 1);
+    assert_eq!((chip.get_vmemory().width(), chip.get_vmemory().height()), (64, 32));
+}
+
+#[test]
+fn op_0nnn_sys_is_not_supported() {
+    let mut chip = boot(Platform::Chip8, "SYS 0x2A0");
+    assert_eq!(chip.cycle(), Err(Chip8Error::UnknownOpcode { pc: 0x200, opcode: 0x02A0 }));
+}
+
+#[test]
+fn unknown_opcodes_fault() {
+    let mut chip = boot(Platform::Chip8, "DW 0x5001");
+    assert_eq!(chip.cycle(), Err(Chip8Error::UnknownOpcode { pc: 0x200, opcode: 0x5001 }));
+    //opcodes from a later platform are unknown to earlier ones
+    let mut chip = boot(Platform::Chip8, "SCD 4");
+    assert_eq!(chip.cycle(), Err(Chip8Error::UnknownOpcode { pc: 0x200, opcode: 0x00C4 }));
+    let mut chip = boot(Platform::SuperChip, "PLANE 1");
+    assert_eq!(chip.cycle(), Err(Chip8Error::UnknownOpcode { pc: 0x200, opcode: 0xF101 }));
+}
+
+#[test]
+fn op_1nnn_jp() {
+    let chip = run(Platform::Chip8, "JP 0x345", 1);
+    assert_eq!(chip.get_program_counter(), 0x345);
+}
+
+#[test]
+fn op_3xkk_se_byte() {
+    let chip = run(Platform::Chip8, "
+        LD V3, 0x42
+        SE V3, 0x42
+        LD V0, 1
+        SE V3, 0x43
+        LD V1, 1
+    ", 4);
+    assert_eq!((register(&chip, 0), register(&chip, 1)), (0, 1));
+}
+
+#[test]
+fn op_4xkk_sne_byte() {
+    let chip = run(Platform::Chip8, "
+        LD V3, 0x42
+        SNE V3, 0x42
+        LD V0, 1
+        SNE V3, 0x43
+        LD V1, 1
+    ", 4);
+    assert_eq!((register(&chip, 0), register(&chip, 1)), (1, 0));
+}
+
+#[test]
+fn op_5xy0_se_registers() {
+    let chip = run(Platform::Chip8, "
+        LD V3, 7
+        LD V4, 7
+        SE V3, V4
+        LD V0, 1
+        LD V4, 8
+        SE V3, V4
+        LD V1, 1
+    ", 6);
+    assert_eq!((register(&chip, 0), register(&chip, 1)), (0, 1));
+}
+
+#[test]
+fn op_5xy2_save_range() {
+    let chip = run(Platform::XoChip, "
+        LD V1, 0x11
+        LD V2, 0x22
+        LD V3, 0x33
+        LD I, 0x400
+        SAVE V1, V3
+        LD I, 0x410
+        SAVE V3, V1
+    ", 7);
+    assert_eq!(&chip.get_memory()[0x400..0x404], &[0x11, 0x22, 0x33, 0]);
+    assert_eq!(&chip.get_memory()[0x410..0x413], &[0x33, 0x22, 0x11]);
+    assert_eq!(chip.get_index_register(), 0x410, "5xy2 never moves I");
+}
+
+#[test]
+fn op_5xy3_load_range() {
+    let chip = run(Platform::XoChip, "
+            LD I, data
+            LOAD V4, V6
+            LOAD VA, V8
+            JP $
+        data:
+            DB 1, 2, 3
+    ", 3);
+    assert_eq!(&chip.get_registers()[4..7], &[1, 2, 3]);
+    assert_eq!(&chip.get_registers()[8..11], &[3, 2, 1]);
+}
+
+#[test]
+fn op_6xkk_ld_byte() {
+    let chip = run(Platform::Chip8, "LD VE, 0xA5", 1);
+    assert_eq!(register(&chip, 0xE), 0xA5);
+}
+
+#[test]
+fn op_7xkk_add_byte() {
+    let chip = run(Platform::Chip8, "
+        LD VF, 0x33
+        LD V0, 0xFE
+        ADD V0, 3
+    ", 3);
+    assert_eq!(register(&chip, 0), 0x01);
+    assert_eq!(register(&chip, 0xF), 0x33, "7xkk never touches VF");
+}
+
+#[test]
+fn op_8xy0_ld_registers() {
+    let chip = run(Platform::Chip8, "
+        LD V1, 0x5A
+        LD V2, V1
+    ", 2);
+    assert_eq!(register(&chip, 2), 0x5A);
+}
+
+#[test]
+fn op_8xy1_or() {
+    let chip = run(Platform::XoChip, "
+        LD V1, 0x0C
+        LD V2, 0x0A
+        OR V1, V2
+    ", 3);
+    assert_eq!(register(&chip, 1), 0x0E);
+}
+
+#[test]
+fn op_8xy2_and() {
+    let chip = run(Platform::XoChip, "
+        LD V1, 0x0C
+        LD V2, 0x0A
+        AND V1, V2
+    ", 3);
+    assert_eq!(register(&chip, 1), 0x08);
+}
+
+#[test]
+fn op_8xy3_xor() {
+    let chip = run(Platform::XoChip, "
+        LD V1, 0x0C
+        LD V2, 0x0A
+        XOR V1, V2
+    ", 3);
+    assert_eq!(register(&chip, 1), 0x06);
+}
+
+#[test]
+fn op_8xy4_add_registers() {
+    let chip = run(Platform::Chip8, "
+        LD V1, 0xF0
+        LD V2, 0x0F
+        ADD V1, V2
+        LD V3, VF
+        ADD V1, V2
+        LD V4, VF
+    ", 6);
+    assert_eq!((register(&chip, 3), register(&chip, 1), register(&chip, 4)), (0, 0x0E, 1));
+}
+
+#[test]
+fn op_8xy5_sub() {
+    let chip = run(Platform::Chip8, "
+        LD V1, 9
+        LD V2, 4
+        SUB V1, V2
+        LD V3, VF
+        SUB V2, V1
+        LD V4, VF
+    ", 6);
+    assert_eq!((register(&chip, 1), register(&chip, 3)), (5, 1));
+    assert_eq!((register(&chip, 2), register(&chip, 4)), (0xFF, 0));
+}
+
+#[test]
+fn op_8xy6_shr() {
+    let chip = run(Platform::SuperChip, "
+        LD V1, 0x05
+        SHR V1
+        LD V2, VF
+        SHR V1
+        LD V3, VF
+    ", 5);
+    assert_eq!((register(&chip, 1), register(&chip, 2), register(&chip, 3)), (0x01, 1, 0));
+}
+
+#[test]
+fn op_8xy7_subn() {
+    let chip = run(Platform::Chip8, "
+        LD V1, 4
+        LD V2, 9
+        SUBN V1, V2
+        LD V3, VF
+        LD V4, 9
+        LD V5, 4
+        SUBN V4, V5
+        LD V6, VF
+    ", 8);
+    assert_eq!((register(&chip, 1), register(&chip, 3)), (5, 1), "Vx = Vy - Vx");
+    assert_eq!((register(&chip, 4), register(&chip, 6)), (0xFB, 0));
+}
+
+#[test]
+fn op_8xye_shl() {
+    let chip = run(Platform::SuperChip, "
+        LD V1, 0xC0
+        SHL V1
+        LD V2, VF
+        LD V3, 0x01
+        SHL V3
+        LD V4, VF
+    ", 6);
+    assert_eq!((register(&chip, 1), register(&chip, 2)), (0x80, 1));
+    assert_eq!((register(&chip, 3), register(&chip, 4)), (0x02, 0));
+}
+
+#[test]
+fn op_9xy0_sne_registers() {
+    let chip = run(Platform::Chip8, "
+        LD V3, 7
+        LD V4, 7
+        SNE V3, V4
+        LD V0, 1
+        LD V4, 8
+        SNE V3, V4
+        LD V1, 1
+    ", 6);
+    assert_eq!((register(&chip, 0), register(&chip, 1)), (1, 0));
+}
+
+#[test]
+fn op_annn_ld_i() {
+    let chip = run(Platform::Chip8, "LD I, 0xABC", 1);
+    assert_eq!(chip.get_index_register(), 0xABC);
+}
+
+#[test]
+fn op_bnnn_jp_v0() {
+    let chip = run(Platform::Chip8, "
+        LD V0, 0x10
+        JP V0, 0x300
+    ", 2);
+    assert_eq!(chip.get_program_counter(), 0x310);
+}
+
+#[test]
+fn op_cxkk_rnd_is_masked() {
+    let mut chip = boot(Platform::Chip8, "
+        loop:
+            RND V0, 0x0F
+            RND V1, 0
+            JP loop
+    ");
+    for _ in 0..100 {
+        step(&mut chip, 3);
+        assert!(register(&chip, 0) <= 0x0F);
+        assert_eq!(register(&chip, 1), 0);
+    }
+}
+
+#[test]
+fn op_cxkk_rnd_follows_the_seed() {
+    let numbers = |chip: &mut Processor, count: usize| -> Vec<u8> {
+        (0..count).map(|_| {
+            step(chip, 2);
+            register(chip, 0)
+        }).collect()
+    };
+    let seeded = |mode: RngMode, seed: u64| {
+        let mut chip = boot(Platform::Chip8, "
+            loop:
+                RND V0, 0xFF
+                JP loop
+        ");
+        chip.set_rng(Rng::new(mode, seed));
+        chip
+    };
+    for mode in [RngMode::SplitMix, RngMode::Vip] {
+        assert_eq!(numbers(&mut seeded(mode, 42), 100), numbers(&mut seeded(mode, 42), 100), "{:?}", mode);
+        //save states carry the generator along
+        let mut chip = seeded(mode, 42);
+        numbers(&mut chip, 10);
+        let state = chip.save_state();
+        let after = numbers(&mut chip, 50);
+        chip.load_state(&state).unwrap();
+        assert_eq!(numbers(&mut chip, 50), after, "{:?}", mode);
+    }
+    assert_ne!(numbers(&mut seeded(RngMode::SplitMix, 1), 16), numbers(&mut seeded(RngMode::SplitMix, 2), 16));
+}
+
+#[test]
+fn op_dxyn_drw() {
+    let chip = run(Platform::XoChip, "
+            LD V0, 62
+            LD V1, 3
+            LD I, sprite
+            DRW V0, V1, 2
+            JP $
+        sprite:
+            DB 0xA0, 0x40
+    ", 4);
+    let framebuffer = chip.get_vmemory();
+    assert_eq!((framebuffer.get(62, 3), framebuffer.get(63, 3), framebuffer.get(0, 3)), (1, 0, 1), "wraps on XO-CHIP");
+    assert_eq!((framebuffer.get(63, 4), framebuffer.get(62, 4)), (1, 0));
+    assert_eq!(framebuffer.pixels().iter().filter(|pixel| **pixel != 0).count(), 3);
+    assert_eq!(register(&chip, 0xF), 0);
+}
+
+#[test]
+fn op_dxyn_sets_vf_on_collision() {
+    let chip = run(Platform::XoChip, "
+            LD I, sprite
+            DRW V0, V1, 1
+            LD V2, VF
+            DRW V0, V1, 1
+            JP $
+        sprite:
+            DB 0x80
+    ", 4);
+    assert_eq!((register(&chip, 2), register(&chip, 0xF)), (0, 1));
+    assert_eq!(chip.get_vmemory().get(0, 0), 0);
+}
+
+#[test]
+fn op_dxy0_draws_16x16() {
+    let chip = run(Platform::SuperChip, "
+            HIGH
+            LD I, sprite
+            DRW V0, V0, 0
+            JP $
+        sprite:
+            DW 0x8001, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xFFFF
+    ", 3);
+    let framebuffer = chip.get_vmemory();
+    assert_eq!((framebuffer.get(0, 0), framebuffer.get(15, 0), framebuffer.get(1, 0)), (1, 1, 0));
+    assert_eq!((0..16).map(|x| framebuffer.get(x, 15)).sum::<u8>(), 16);
+    assert_eq!(framebuffer.get(0, 16), 0);
+}
+
+#[test]
+fn op_ex9e_skp() {
+    let mut chip = boot(Platform::Chip8, "
+        LD V0, 0x7
+        SKP V0
+        LD V1, 1
+        SKP V0
+        LD V2, 1
+    ");
+    step(&mut chip, 3);
+    chip.set_key(0x7, true);
+    step(&mut chip, 1);
+    assert_eq!((register(&chip, 1), chip.get_program_counter()), (1, 0x20A));
+}
+
+#[test]
+fn op_exa1_sknp() {
+    let mut chip = boot(Platform::Chip8, "
+        LD V0, 0x7
+        SKNP V0
+        LD V1, 1
+        SKNP V0
+        LD V2, 1
+    ");
+    step(&mut chip, 2);
+    assert_eq!(chip.get_program_counter(), 0x206);
+    chip.set_key(0x7, true);
+    step(&mut chip, 2);
+    assert_eq!(register(&chip, 2), 1);
+}
+
+#[test]
+fn op_f000_ld_i_long() {
+    let chip = run(Platform::XoChip, "
+        LD I, LONG 0xBEEF
+    ", 1);
+    assert_eq!((chip.get_index_register(), chip.get_program_counter()), (0xBEEF, 0x204));
+    //skips jump over all four bytes
+    let chip = run(Platform::XoChip, "
+        SE V0, 0
+        LD I, LONG 0xBEEF
+        LD V1, 1
+    ", 2);
+    assert_eq!((chip.get_index_register(), register(&chip, 1)), (0, 1));
+}
+
+#[test]
+fn op_fn01_plane() {
+    let chip = run(Platform::XoChip, "
+            PLANE 2
+            LD I, sprite
+            DRW V0, V0, 1
+            PLANE 3
+            LD V1, 8
+            DRW V1, V0, 1
+            JP $
+        sprite:
+            DB 0x80
+            DB 0x80
+    ", 6);
+    assert_eq!(chip.get_vmemory().get(0, 0), 2);
+    assert_eq!(chip.get_vmemory().get(8, 0), 3);
+}
+
+#[test]
+fn op_f002_audio() {
+    let chip = run(Platform::XoChip, "
+            LD I, pattern
+            AUDIO
+            JP $
+        pattern:
+            DB 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15
+    ", 2);
+    let pattern: Vec<u8> = (0..16).collect();
+    assert_eq!(chip.get_audio_pattern().map(|pattern| pattern.to_vec()), Some(pattern));
+}
+
+#[test]
+fn op_fx07_fx15_delay_timer() {
+    let mut chip = boot(Platform::Chip8, "
+        LD V0, 10
+        LD DT, V0
+        JP $
+    ");
+    chip.run_frame(3).unwrap();
+    assert_eq!(chip.get_timers().0, 9);
+    let mut chip = boot(Platform::Chip8, "
+        LD V0, 10
+        LD DT, V0
+        LD V1, DT
+    ");
+    step(&mut chip, 3);
+    assert_eq!(register(&chip, 1), 10);
+}
+
+#[test]
+fn op_fx0a_waits_for_key() {
+    let mut chip = boot(Platform::Chip8, "LD V5, K");
+    step(&mut chip, 3);
+    assert_eq!(chip.get_program_counter(), 0x200);
+    chip.set_key(0xC, true);
+    step(&mut chip, 1);
+    assert_eq!((register(&chip, 5), chip.get_program_counter()), (0xC, 0x202));
+}
+
+#[test]
+fn op_fx18_sound_timer() {
+    let mut chip = boot(Platform::Chip8, "
+        LD V0, 4
+        LD ST, V0
+        JP $
+    ");
+    chip.run_frame(3).unwrap();
+    assert_eq!(chip.get_timers().1, 3);
+}
+
+#[test]
+fn op_fx1e_add_i() {
+    let chip = run(Platform::Chip8, "
+        LD I, 0xFF0
+        LD V0, 0x20
+        ADD I, V0
+    ", 3);
+    assert_eq!(chip.get_index_register(), 0x1010);
+    assert_eq!(register(&chip, 0xF), 0, "Fx1E leaves VF alone");
+}
+
+#[test]
+fn op_fx29_ld_f() {
+    for digit in 0..16u8 {
+        let source = format!("
+            LD V3, {}
+            LD F, V3
+        ", digit);
+        let chip = run(Platform::Chip8, &source, 2);
+        let index = chip.get_index_register() as usize;
+        let glyph = &FONTSET[digit as usize * 5..digit as usize * 5 + 5];
+        assert_eq!(&chip.get_memory()[index..index + 5], glyph, "digit {:X}", digit);
+    }
+}
+
+#[test]
+fn op_fx29_uses_the_low_nibble() {
+    let large = run(Platform::Chip8, "
+        LD V3, 0xFA
+        LD F, V3
+    ", 2);
+    let small = run(Platform::Chip8, "
+        LD V3, 0x0A
+        LD F, V3
+    ", 2);
+    assert_eq!(large.get_index_register(), small.get_index_register());
+}
+
+#[test]
+fn op_fx30_ld_hf() {
+    let chip = run(Platform::SuperChip, "
+        LD V3, 0x17
+        LD HF, V3
+    ", 2);
+    let index = chip.get_index_register() as usize;
+    assert_eq!(&chip.get_memory()[index..index + 10], &BIG_FONTSET[70..80]);
+}
+
+#[test]
+fn op_fx33_bcd() {
+    let chip = run(Platform::Chip8, "
+        LD V0, 207
+        LD I, 0x400
+        LD B, V0
+    ", 3);
+    assert_eq!(&chip.get_memory()[0x400..0x403], &[2, 0, 7]);
+    assert_eq!(chip.get_index_register(), 0x400);
+}
+
+#[test]
+fn op_fx3a_pitch() {
+    let chip = run(Platform::XoChip, "
+        LD V0, 112
+        PITCH V0
+    ", 2);
+    assert_eq!(chip.get_playback_rate(), 8000.0);
+}
+
+#[test]
+fn op_fx55_store_registers() {
+    let chip = run(Platform::SuperChip, "
+        LD V0, 1
+        LD V1, 2
+        LD V2, 3
+        LD I, 0x400
+        LD [I], V1
+    ", 5);
+    assert_eq!(&chip.get_memory()[0x400..0x403], &[1, 2, 0]);
+}
+
+#[test]
+fn op_fx65_load_registers() {
+    let chip = run(Platform::SuperChip, "
+            LD I, data
+            LD V2, [I]
+            JP $
+        data:
+            DB 9, 8, 7, 6
+    ", 2);
+    assert_eq!(&chip.get_registers()[..4], &[9, 8, 7, 0]);
+}
+
+#[test]
+fn op_fx55_fx65_wrap_i_on_xo_chip() {
+    let chip = run(Platform::XoChip, "
+        LD V0, 0xAB
+        LD I, LONG 0xFFFF
+        LD [I], V0
+        LD I, LONG 0xFFFF
+        LD V0, [I]
+    ", 5);
+    assert_eq!((chip.get_memory()[0xFFFF], register(&chip, 0)), (0xAB, 0xAB));
+    assert_eq!(chip.get_index_register(), 0, "I wraps past the end of XO-CHIP memory");
+}
+
+#[test]
+fn op_fx55_faults_past_memory() {
+    let mut chip = boot(Platform::Chip8, "
+        LD I, 0xFFE
+        LD [I], V3
+    ");
+    step(&mut chip, 1);
+    assert_eq!(chip.cycle(), Err(Chip8Error::MemoryOutOfBounds { pc: 0x202, opcode: 0xF355, address: 0x1000 }));
+}
+
+#[test]
+fn op_fx75_fx85_rpl_flags() {
+    let chip = run(Platform::SuperChip, "
+        LD V0, 0xAA
+        LD V1, 0xBB
+        LD V2, 0xCC
+        LD R, V1
+        LD V0, 0
+        LD V1, 0
+        LD V2, 0
+        LD V2, R
+    ", 8);
+    assert_eq!(&chip.get_registers()[..3], &[0xAA, 0xBB, 0], "only V0-V1 were saved");
+}
@@ -0,0 +1,137 @@
+//One test per quirk, each running the same program with the quirk on and off.
+
+mod common;
+
+use chip8rs_core::{Platform, Quirks};
+use common::{boot_with, register, run_with};
+
+fn with(quirk: fn(&mut Quirks), enabled: bool) -> Quirks {
+    let mut quirks = Quirks { shift_uses_vy: false, load_store_increments_i: false, jump_uses_vx: false,
+        logic_resets_vf: false, wrap_sprites: false, display_wait: false };
+    if enabled {
+        quirk(&mut quirks);
+    }
+    quirks
+}
+
+#[test]
+fn shift_uses_vy() {
+    let source = "
+        LD V1, 0x10
+        LD V2, 0x06
+        SHR V1, V2
+        LD V3, 0x10
+        SHL V3, V2
+    ";
+    let on = run_with(Platform::Chip8, with(|quirks| quirks.shift_uses_vy = true, true), source, 5);
+    assert_eq!((register(&on, 1), register(&on, 3)), (0x03, 0x0C));
+    let off = run_with(Platform::Chip8, with(|quirks| quirks.shift_uses_vy = true, false), source, 5);
+    assert_eq!((register(&off, 1), register(&off, 3)), (0x08, 0x20));
+}
+
+#[test]
+fn load_store_increments_i() {
+    let source = "
+        LD I, 0x400
+        LD [I], V2
+        LD V1, [I]
+    ";
+    let on = run_with(Platform::Chip8, with(|quirks| quirks.load_store_increments_i = true, true), source, 3);
+    assert_eq!(on.get_index_register(), 0x405);
+    let off = run_with(Platform::Chip8, with(|quirks| quirks.load_store_increments_i = true, false), source, 3);
+    assert_eq!(off.get_index_register(), 0x400);
+}
+
+#[test]
+fn jump_uses_vx() {
+    let source = "
+        LD V0, 0x01
+        LD V3, 0x10
+        JP V0, 0x320
+    ";
+    let on = run_with(Platform::SuperChip, with(|quirks| quirks.jump_uses_vx = true, true), source, 3);
+    assert_eq!(on.get_program_counter(), 0x330);
+    let off = run_with(Platform::SuperChip, with(|quirks| quirks.jump_uses_vx = true, false), source, 3);
+    assert_eq!(off.get_program_counter(), 0x321);
+}
+
+#[test]
+fn logic_resets_vf() {
+    for op in ["OR", "AND", "XOR"] {
+        let source = format!("
+            LD VF, 0x55
+            {} V1, V2
+        ", op);
+        let on = run_with(Platform::Chip8, with(|quirks| quirks.logic_resets_vf = true, true), &source, 2);
+        assert_eq!(register(&on, 0xF), 0, "{}", op);
+        let off = run_with(Platform::Chip8, with(|quirks| quirks.logic_resets_vf = true, false), &source, 2);
+        assert_eq!(register(&off, 0xF), 0x55, "{}", op);
+    }
+}
+
+#[test]
+fn wrap_sprites() {
+    let source = "
+            LD V0, 60
+            LD V1, 31
+            LD I, sprite
+            DRW V0, V1, 2
+            JP $
+        sprite:
+            DB 0xFF, 0xFF
+    ";
+    let on = run_with(Platform::XoChip, with(|quirks| quirks.wrap_sprites = true, true), source, 4);
+    assert_eq!((on.get_vmemory().get(3, 0), on.get_vmemory().get(4, 0)), (1, 0));
+    assert_eq!(on.get_vmemory().pixels().iter().filter(|pixel| **pixel != 0).count(), 16);
+    let off = run_with(Platform::XoChip, with(|quirks| quirks.wrap_sprites = true, false), source, 4);
+    assert_eq!(off.get_vmemory().get(3, 0), 0);
+    assert_eq!(off.get_vmemory().pixels().iter().filter(|pixel| **pixel != 0).count(), 4);
+}
+
+#[test]
+fn sprites_start_wrapped_either_way() {
+    //only the part past the edge is clipped, the start position always wraps
+    let source = "
+            LD V0, 66
+            LD V1, 33
+            LD I, sprite
+            DRW V0, V1, 1
+            JP $
+        sprite:
+            DB 0x80
+    ";
+    let chip = run_with(Platform::XoChip, with(|quirks| quirks.wrap_sprites = true, false), source, 4);
+    assert_eq!(chip.get_vmemory().get(2, 1), 1);
+}
+
+#[test]
+fn display_wait() {
+    let source = "
+            LD I, sprite
+        loop:
+            DRW V0, V0, 1
+            ADD V1, 1
+            JP loop
+        sprite:
+            DB 0x80
+    ";
+    let mut on = boot_with(Platform::Chip8, with(|quirks| quirks.display_wait = true, true), source);
+    on.run_frame(30).unwrap();
+    assert_eq!(register(&on, 1), 0, "the first draw ends the frame");
+    on.run_frame(30).unwrap();
+    assert_eq!(register(&on, 1), 1);
+    let mut off = boot_with(Platform::Chip8, with(|quirks| quirks.display_wait = true, false), source);
+    off.run_frame(30).unwrap();
+    assert_eq!(register(&off, 1), 10);
+}
+
+#[test]
+fn presets() {
+    assert_eq!(Quirks::from_preset("VIP"), Some(Quirks::vip()));
+    assert_eq!(Quirks::from_preset("chip-48"), Some(Quirks::chip48()));
+    assert_eq!(Quirks::from_preset("superchip"), Some(Quirks::schip()));
+    assert_eq!(Quirks::from_preset("octo"), Some(Quirks::modern()));
+    assert_eq!(Quirks::from_preset("gameboy"), None);
+    assert_eq!(Platform::Chip8.default_quirks(), Quirks::vip());
+    assert_eq!(Quirks::default(), Quirks::modern());
+}
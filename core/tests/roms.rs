@@ -0,0 +1,58 @@
+//Runs the conformance ROMs in tests/roms headlessly and compares the final framebuffer with a
+//golden hash. The ROMs are assembled from source at test time; each draws a tick per passing case and
+//a cross per failing one, so on a mismatch the printed screen shows which case broke.
+//
+//Golden hashes were recorded from screens checked by hand and are the ones
+//`chip8-headless --output hash --ips 1200 --frames 600` prints for the same ROM. When a change is
+//meant to alter a screen, look at the printed output before updating the hash.
+
+mod common;
+
+use chip8rs_core::{assemble, sha1_hex, Platform};
+use common::{boot, screen, screen_hash};
+
+const INSTRUCTIONS_PER_FRAME: u32 = 20;
+//every ROM reaches its end loop or exits well within this
+const FRAMES: usize = 600;
+
+fn check_rom(source: &str, platform: Platform, golden: &str) {
+    let mut chip = boot(platform, source);
+    for _ in 0..FRAMES {
+        if chip.has_exited() {
+            break;
+        }
+        chip.run_frame(INSTRUCTIONS_PER_FRAME).unwrap_or_else(|error| panic!("{}\n{}", error, screen(&chip)));
+    }
+    assert_eq!(screen_hash(&chip), golden, "screen doesn't match:\n{}", screen(&chip));
+}
+
+#[test]
+fn flags_rom() {
+    check_rom(include_str!("roms/flags.asm"), Platform::Chip8, "393b0dcfbc7189f5");
+}
+
+#[test]
+fn opcodes_rom() {
+    check_rom(include_str!("roms/opcodes.asm"), Platform::Chip8, "87b3e1eda2b8e630");
+}
+
+#[test]
+fn schip_rom() {
+    check_rom(include_str!("roms/schip.asm"), Platform::SuperChip, "adbc66097899f145");
+}
+
+#[test]
+fn xochip_rom() {
+    check_rom(include_str!("roms/xochip.asm"), Platform::XoChip, "20faf1304d7cd4a4");
+}
+
+//data/roms.toml lists these ROMs by hash, so a change to a ROM's source needs its entry updated too
+#[test]
+fn rom_database_has_the_conformance_roms() {
+    let database = include_str!("../../data/roms.toml");
+    let sources = [include_str!("roms/flags.asm"), include_str!("roms/opcodes.asm"), include_str!("roms/schip.asm"), include_str!("roms/xochip.asm")];
+    for source in sources {
+        let hash = sha1_hex(&assemble(source).unwrap());
+        assert!(database.contains(&format!("[{}]", hash)), "no entry for {} in data/roms.toml", hash);
+    }
+}
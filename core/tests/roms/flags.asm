@@ -0,0 +1,216 @@
+; 8xy1-8xyE results and VF, including the edge cases: equal operands, VF as the destination and
+; VF as an operand. Each case draws a tick, or a cross if the result or the flag is wrong.
+; CHIP-8 with VIP quirks, so shifts read Vy and logic ops reset VF.
+
+; OR, AND, XOR reset VF
+    LD VF, 0x55
+    LD V1, 0x0C
+    LD V2, 0x0A
+    OR V1, V2
+    LD V3, VF
+    LD V4, 0x0E
+    LD V5, 0
+    CALL check
+
+    LD VF, 0x55
+    LD V1, 0x0C
+    AND V1, V2
+    LD V3, VF
+    LD V4, 0x08
+    CALL check
+
+    LD VF, 0x55
+    LD V1, 0x0C
+    XOR V1, V2
+    LD V3, VF
+    LD V4, 0x06
+    CALL check
+
+; 8xy4
+    LD V1, 0xF0
+    LD V2, 0x0F
+    ADD V1, V2
+    LD V3, VF
+    LD V4, 0xFF
+    LD V5, 0
+    CALL check
+
+    LD V1, 0xF0
+    LD V2, 0x20
+    ADD V1, V2
+    LD V3, VF
+    LD V4, 0x10
+    LD V5, 1
+    CALL check
+
+    LD V1, 0xFF
+    LD V2, 0x01
+    ADD V1, V2
+    LD V3, VF
+    LD V4, 0x00
+    CALL check
+
+; 8xy5, VF = 1 unless it borrows
+    LD V1, 9
+    LD V2, 4
+    SUB V1, V2
+    LD V3, VF
+    LD V4, 5
+    LD V5, 1
+    CALL check
+
+    LD V1, 5
+    LD V2, 5
+    SUB V1, V2
+    LD V3, VF
+    LD V4, 0
+    CALL check
+
+    LD V1, 4
+    LD V2, 9
+    SUB V1, V2
+    LD V3, VF
+    LD V4, 0xFB
+    LD V5, 0
+    CALL check
+
+; 8xy7, Vx = Vy - Vx
+    LD V1, 4
+    LD V2, 9
+    SUBN V1, V2
+    LD V3, VF
+    LD V4, 5
+    LD V5, 1
+    CALL check
+
+    LD V1, 5
+    LD V2, 5
+    SUBN V1, V2
+    LD V3, VF
+    LD V4, 0
+    CALL check
+
+    LD V1, 9
+    LD V2, 4
+    SUBN V1, V2
+    LD V3, VF
+    LD V4, 0xFB
+    LD V5, 0
+    CALL check
+
+; 8xy6 / 8xyE shift Vy on the VIP
+    LD V1, 0
+    LD V2, 0x05
+    SHR V1, V2
+    LD V3, VF
+    LD V4, 0x02
+    LD V5, 1
+    CALL check
+
+    LD V2, 0x04
+    SHR V1, V2
+    LD V3, VF
+    LD V4, 0x02
+    LD V5, 0
+    CALL check
+
+    LD V2, 0x81
+    SHL V1, V2
+    LD V3, VF
+    LD V4, 0x02
+    LD V5, 1
+    CALL check
+
+    LD V2, 0x41
+    SHL V1, V2
+    LD V3, VF
+    LD V4, 0x82
+    LD V5, 0
+    CALL check
+
+; VF as the destination ends up holding the flag
+    LD VF, 0xF0
+    LD V2, 0x20
+    ADD VF, V2
+    LD V1, VF
+    LD V3, VF
+    LD V4, 1
+    LD V5, 1
+    CALL check
+
+    LD VF, 0x10
+    LD V2, 0x01
+    SUB VF, V2
+    LD V1, VF
+    LD V3, VF
+    CALL check
+
+    LD VF, 0x10
+    LD V2, 0x01
+    SUBN VF, V2
+    LD V1, VF
+    LD V3, VF
+    LD V4, 0
+    LD V5, 0
+    CALL check
+
+    LD V2, 0x02
+    SHR VF, V2
+    LD V1, VF
+    LD V3, VF
+    CALL check
+
+    LD V2, 0x80
+    SHL VF, V2
+    LD V1, VF
+    LD V3, VF
+    LD V4, 1
+    LD V5, 1
+    CALL check
+
+; VF as an operand is read before the flag replaces it
+    LD V1, 0x10
+    LD VF, 0xF8
+    ADD V1, VF
+    LD V3, VF
+    LD V4, 0x08
+    CALL check
+
+    LD V1, 0x10
+    LD VF, 0x20
+    SUB V1, VF
+    LD V3, VF
+    LD V4, 0xF0
+    LD V5, 0
+    CALL check
+
+    LD V1, 0x10
+    LD VF, 0x20
+    SUBN V1, VF
+    LD V3, VF
+    LD V4, 0x10
+    LD V5, 1
+    CALL check
+
+end:
+    JP end
+
+; tick if V1 == V4 and V3 == V5, cross otherwise, drawn at (V8, V9) in rows of 12
+check:
+    LD I, tick
+    SE V1, V4
+    LD I, cross
+    SE V3, V5
+    LD I, cross
+    DRW V8, V9, 4
+    ADD V8, 5
+    SE V8, 60
+    RET
+    LD V8, 0
+    ADD V9, 5
+    RET
+
+tick:
+    DB 0x10, 0x20, 0xA0, 0x40
+cross:
+    DB 0xA0, 0x40, 0xA0, 0x00
@@ -0,0 +1,170 @@
+; Skips, jumps, calls and the Fx memory opcodes on CHIP-8 with VIP quirks. Each case draws a tick,
+; or a cross if V1 != V4 or V3 != V5.
+
+; 3xkk / 4xkk / 5xy0 / 9xy0 skip when they should
+    LD V6, 5
+    LD V7, 5
+    LD V1, 0
+    SE V6, 5
+    LD V1, 1
+    LD V4, 0
+    CALL check_result
+
+    SE V6, 6
+    LD V1, 2
+    LD V4, 2
+    CALL check_result
+
+    LD V1, 0
+    SNE V6, 6
+    LD V1, 1
+    LD V4, 0
+    CALL check_result
+
+    SNE V6, 5
+    LD V1, 2
+    LD V4, 2
+    CALL check_result
+
+    LD V1, 0
+    SE V6, V7
+    LD V1, 1
+    LD V4, 0
+    CALL check_result
+
+    LD V1, 0
+    SNE V6, V7
+    LD V1, 1
+    LD V4, 1
+    CALL check_result
+
+; 2nnn / 00EE
+    LD V1, 0
+    CALL set_seven
+    LD V4, 7
+    CALL check_result
+
+; Bnnn adds V0
+    LD V4, 3
+    LD V0, 4
+    JP V0, table
+table:
+    LD V1, 1
+    LD V1, 2
+    LD V1, 3
+    CALL check_result
+
+; 7xkk wraps and leaves VF alone
+    LD VF, 0x55
+    LD V1, 0xFF
+    ADD V1, 2
+    LD V3, VF
+    LD V4, 1
+    LD V5, 0x55
+    CALL check
+
+; Fx33 BCD
+    LD V0, 195
+    LD I, scratch
+    LD B, V0
+    LD I, scratch
+    LD V2, [I]
+    LD V3, V2
+    LD V4, 9
+    LD V5, 5
+    CALL check
+
+; Fx55 / Fx65 round trip, then the VIP leaves I past the last register
+    LD V0, 0x12
+    LD V1, 0x34
+    LD V2, 0x56
+    LD I, scratch
+    LD [I], V2
+    LD V0, 0
+    LD V1, 0
+    LD I, scratch
+    LD V1, [I]
+    LD V3, V0
+    LD V4, 0x34
+    LD V5, 0x12
+    CALL check
+
+    LD I, scratch
+    LD V1, [I]
+    LD V0, [I]
+    LD V1, V0
+    LD V4, 0x56
+    CALL check_result
+
+; Fx1E
+    LD I, data
+    LD V0, 3
+    ADD I, V0
+    LD V0, [I]
+    LD V1, V0
+    LD V4, 0x78
+    CALL check_result
+
+; Fx29 points at the digit's glyph, only the low nibble counts
+    LD V0, 0x07
+    LD F, V0
+    LD V1, [I]
+    LD V3, V0
+    LD V4, 0x10
+    LD V5, 0xF0
+    CALL check
+
+    LD V0, 0xFA
+    LD F, V0
+    LD V1, [I]
+    LD V3, V0
+    LD V4, 0x90
+    LD V5, 0xF0
+    CALL check
+
+; Cxkk with a zero mask
+    LD V1, 0xFF
+    RND V1, 0
+    LD V4, 0
+    CALL check_result
+
+; 8xy0
+    LD V2, 0x3C
+    LD V1, V2
+    LD V4, 0x3C
+    CALL check_result
+
+end:
+    JP end
+
+set_seven:
+    LD V1, 7
+    RET
+
+; only compare V1 with V4
+check_result:
+    LD V3, 0
+    LD V5, 0
+; tick if V1 == V4 and V3 == V5, cross otherwise, drawn at (V8, V9) in rows of 12
+check:
+    LD I, tick
+    SE V1, V4
+    LD I, cross
+    SE V3, V5
+    LD I, cross
+    DRW V8, V9, 4
+    ADD V8, 5
+    SE V8, 60
+    RET
+    LD V8, 0
+    ADD V9, 5
+    RET
+
+tick:
+    DB 0x10, 0x20, 0xA0, 0x40
+cross:
+    DB 0xA0, 0x40, 0xA0, 0x00
+data:
+    DB 0x12, 0x34, 0x56, 0x78
+scratch:
+    DB 0, 0, 0, 0
@@ -0,0 +1,34 @@
+; SUPER-CHIP drawing: hires big font digits 0-9, a 16x16 sprite and scrolling. The ROM draws in the
+; top left and scrolls everything 4 right and 4 down, then draws the box again as a reference
+; point, so a correct screen has the digits starting at (6, 6) with two boxes side by side at y = 24.
+
+    HIGH
+    LD V0, 0
+    LD V1, 2
+    LD V2, 2
+digits:
+    LD HF, V0
+    DRW V1, V2, 10
+    ADD V1, 10
+    ADD V0, 1
+    SE V0, 10
+    JP digits
+
+    LD V1, 8
+    LD V2, 20
+    LD I, box
+    DRW V1, V2, 0
+
+    SCR
+    SCR
+    SCL
+    SCD 4
+
+    LD V1, 40
+    LD V2, 24
+    DRW V1, V2, 0
+    EXIT
+
+box:
+    DW 0xFFFF, 0x8001, 0x8001, 0x8181, 0x8181, 0x8001, 0x8001, 0x8001
+    DW 0x8001, 0x8001, 0x8001, 0x8181, 0x8181, 0x8001, 0x8001, 0xFFFF
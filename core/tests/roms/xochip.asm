@@ -0,0 +1,102 @@
+; XO-CHIP additions. The top rows are ticks (or crosses) for long loads, register ranges and the
+; 16 RPL flags. Below them three 8x8 sprites at y = 22: plane 1 at x = 0, plane 2 at x = 10 (drawn
+; 2 rows lower, then only plane 2 is scrolled up) and both planes at x = 20, a solid block in
+; plane 1 with a frame in plane 2.
+
+; F000 nnnn loads a 16 bit address and skips treat it as one instruction
+    LD V6, 5
+    LD V1, 0
+    SE V6, 5
+    LD I, LONG data
+    LD V1, [I]
+    LD V4, 0
+    CALL check_result
+
+    LD I, LONG data
+    LD V1, [I]
+    LD V3, V0
+    LD V4, 0x34
+    LD V5, 0x12
+    CALL check
+
+; 5xy2 / 5xy3 in both directions, I doesn't move
+    LD V2, 0xA1
+    LD V3, 0xB2
+    LD V4, 0xC3
+    LD I, scratch
+    SAVE V4, V2
+    LOAD V5, V7
+    LD V1, V5
+    LD V3, V7
+    LD V4, 0xC3
+    LD V5, 0xA1
+    CALL check
+
+    LD I, scratch
+    LOAD V0, V1
+    LD V3, V0
+    LD V4, 0xB2
+    LD V5, 0xC3
+    CALL check
+
+; Fx75 / Fx85 keep all 16 registers
+    LD VF, 0x77
+    LD R, VF
+    LD VF, 0
+    LD VF, R
+    LD V1, VF
+    LD V4, 0x77
+    CALL check_result
+
+; planes
+    LD V1, 0
+    LD V2, 22
+    LD I, solid
+    DRW V1, V2, 8
+
+    PLANE 2
+    LD V1, 10
+    LD V2, 24
+    DRW V1, V2, 8
+    SCU 2
+
+    PLANE 3
+    LD V1, 20
+    LD V2, 22
+    LD I, both
+    DRW V1, V2, 8
+
+end:
+    JP end
+
+; only compare V1 with V4
+check_result:
+    LD V3, 0
+    LD V5, 0
+; tick if V1 == V4 and V3 == V5, cross otherwise, drawn at (V8, V9) in rows of 12
+check:
+    LD I, tick
+    SE V1, V4
+    LD I, cross
+    SE V3, V5
+    LD I, cross
+    DRW V8, V9, 4
+    ADD V8, 5
+    SE V8, 60
+    RET
+    LD V8, 0
+    ADD V9, 5
+    RET
+
+tick:
+    DB 0x10, 0x20, 0xA0, 0x40
+cross:
+    DB 0xA0, 0x40, 0xA0, 0x00
+data:
+    DB 0x12, 0x34
+solid:
+both:
+    DB 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF
+    DB 0xFF, 0x81, 0x81, 0x81, 0x81, 0x81, 0x81, 0xFF
+scratch:
+    DB 0, 0, 0
@@ -0,0 +1,61 @@
+//Runs public test suites the same way roms.rs runs the conformance ROMs. These aren't assembled from
+//source but vendored as binaries under tests/roms/vendor, next to their licences; the README there
+//lists where each file comes from.
+//
+//The binaries aren't checked in yet, so the test is ignored until they are; run it with
+//`cargo test -p chip8rs-core --test suites -- --ignored`. A missing ROM fails it, and so does one
+//with no golden hash yet, printing its screen and hash so the screen can be checked by hand before
+//the hash is recorded here.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use chip8rs_core::{Platform, Processor, FONTSET};
+use common::{screen, screen_hash};
+
+struct Suite {
+    file: &'static str,
+    platform: Platform,
+    instructions_per_frame: u32,
+    frames: usize,
+    //Timendus' menus skip straight to a test when 0x1FF holds its number
+    select: Option<u8>,
+    golden: Option<&'static str>,
+}
+
+//Timendus' chip8-test-suite v4, GPL-3.0
+const SUITES: [Suite; 6] = [
+    Suite { file: "1-chip8-logo.ch8", platform: Platform::Chip8, instructions_per_frame: 20, frames: 60, select: None, golden: None },
+    Suite { file: "2-ibm-logo.ch8", platform: Platform::Chip8, instructions_per_frame: 20, frames: 60, select: None, golden: None },
+    Suite { file: "3-corax+.ch8", platform: Platform::Chip8, instructions_per_frame: 20, frames: 120, select: None, golden: None },
+    Suite { file: "4-flags.ch8", platform: Platform::Chip8, instructions_per_frame: 20, frames: 120, select: None, golden: None },
+    Suite { file: "5-quirks.ch8", platform: Platform::Chip8, instructions_per_frame: 20, frames: 600, select: Some(1), golden: None },
+    Suite { file: "8-scrolling.ch8", platform: Platform::SuperChip, instructions_per_frame: 20, frames: 120, select: Some(1), golden: None },
+];
+
+#[test]
+#[ignore = "the Timendus binaries aren't vendored in tests/roms/vendor yet, see the README there"]
+fn vendored_suites() {
+    let vendor = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/roms/vendor");
+    for suite in &SUITES {
+        let rom = fs::read(vendor.join(suite.file))
+            .unwrap_or_else(|error| panic!("{} isn't vendored in {}: {}", suite.file, vendor.display(), error));
+        let mut chip = Processor::new(FONTSET, suite.platform.default_quirks(), suite.platform);
+        chip.load_rom(&rom).unwrap_or_else(|error| panic!("{}: {}", suite.file, error));
+        if let Some(test) = suite.select {
+            chip.get_bus_mut().poke(0x1FF, test);
+        }
+        for _ in 0..suite.frames {
+            if chip.has_exited() {
+                break;
+            }
+            chip.run_frame(suite.instructions_per_frame).unwrap_or_else(|error| panic!("{}: {}\n{}", suite.file, error, screen(&chip)));
+        }
+        let hash = screen_hash(&chip);
+        match suite.golden {
+            Some(golden) => assert_eq!(hash, golden, "{} screen doesn't match:\n{}", suite.file, screen(&chip)),
+            None => panic!("{} has no golden hash yet, check this screen and record {}:\n{}", suite.file, hash, screen(&chip))
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::f32::consts::PI;
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use sdl2::Sdl;
+use chip8rs_core::Processor;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Waveform {
+    Square,
+    Sine,
+    Triangle,
+    Sawtooth,
+}
+
+impl Waveform {
+    pub fn from_name(name: &str) -> Option<Waveform> {
+        match name.to_lowercase().as_str() {
+            "square" => Some(Waveform::Square),
+            "sine" => Some(Waveform::Sine),
+            "triangle" => Some(Waveform::Triangle),
+            "sawtooth" | "saw" => Some(Waveform::Sawtooth),
+            _ => None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct AudioSettings {
+    //0.0 - 1.0
+    pub volume: f32,
+    //hz of the buzzer tone, XO-CHIP audio patterns use the ROM's pitch instead
+    pub frequency: f32,
+    pub waveform: Waveform,
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> AudioSettings {
+        AudioSettings { volume: 0.25, frequency: 440.0, waveform: Waveform::Square, muted: false }
+    }
+}
+
+//runs on SDL's audio thread, the main loop only swaps the pattern/pitch through AudioDevice::lock
+struct Tone {
+    sample_rate: f32,
+    settings: AudioSettings,
+    phase: f32,
+    //XO-CHIP 128 bit pattern and the samples per second it's played at
+    pattern: Option<[u8; 16]>,
+    pattern_rate: f32,
+}
+
+impl Tone {
+    fn buzzer_sample(&mut self) -> f32 {
+        self.phase = (self.phase + self.settings.frequency / self.sample_rate) % 1.0;
+        match self.settings.waveform {
+            Waveform::Square => if self.phase < 0.5 { 1.0 } else { -1.0 },
+            Waveform::Sine => (self.phase * 2.0 * PI).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * (self.phase - 0.5).abs(),
+            Waveform::Sawtooth => 2.0 * self.phase - 1.0,
+        }
+    }
+
+    fn pattern_sample(&mut self, pattern: [u8; 16]) -> f32 {
+        self.phase = (self.phase + self.pattern_rate / self.sample_rate) % 128.0;
+        let bit = self.phase as usize;
+        if pattern[bit / 8] & (0x80 >> (bit % 8)) != 0 { 1.0 } else { -1.0 }
+    }
+}
+
+impl AudioCallback for Tone {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            let value = match self.pattern {
+                Some(pattern) => self.pattern_sample(pattern),
+                None => self.buzzer_sample()
+            };
+            *sample = value * self.settings.volume;
+        }
+    }
+}
+
+pub struct Audio {
+    device: AudioDevice<Tone>,
+    muted: bool,
+}
+
+impl Audio {
+    pub fn new(sdl_context: &Sdl, settings: AudioSettings) -> Result<Audio, String> {
+        let audio_subsystem = sdl_context.audio()?;
+        let desired_spec = AudioSpecDesired { freq: Some(44_100), channels: Some(1), samples: Some(512) };
+        let device = audio_subsystem.open_playback(None, &desired_spec, |spec| {
+            Tone { sample_rate: spec.freq as f32, settings, phase: 0.0, pattern: None, pattern_rate: 4000.0 }
+        })?;
+        Ok(Audio { device, muted: settings.muted })
+    }
+
+    //plays while the sound timer is running, called once per frame
+    pub fn update(&mut self, chip: &Processor) {
+        let playing = !self.muted && chip.get_timers().1 > 0;
+        if playing {
+            {
+                let mut tone = self.device.lock();
+                tone.pattern = chip.get_audio_pattern();
+                tone.pattern_rate = chip.get_playback_rate();
+            }
+            self.device.resume();
+        } else {
+            self.device.pause();
+        }
+    }
+}
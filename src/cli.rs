@@ -0,0 +1,302 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+use chip8rs_core::{Platform, Quirks, RngMode};
+use chip8rs_core::disasm::Syntax;
+use chip8rs_core::trace::{TraceFilter, TraceFormat};
+use crate::audio::{AudioSettings, Waveform};
+use crate::display::Palette;
+use crate::flicker::FlickerMode;
+
+pub const USAGE: &str = "\
+Usage: chip8rs [OPTIONS] [ROM]
+       chip8rs disasm [OPTIONS] ROM
+       chip8rs asm SOURCE [-o ROM]
+       chip8rs trace TRACE
+
+Runs ROM, or asks for one with a file dialog when no path is given.
+
+Options:
+  --ips <N>             instructions per second (alias --hz) [default: the ROM database's, else 1000]
+  --scale <N>           window pixels per lores CHIP-8 pixel, the window is never below 128x64
+                        [default: 10]
+  --fps <N>             how many of the 60 emulated frames per second are presented [default: 60]
+  --platform <NAME>     chip8, schip or xochip [default: the ROM database's, else xochip]
+  --quirks <PRESET>     vip, chip48, schip or modern [default: the ROM database's, else the platform's preset]
+  --seed <N>            seed for Cxkk random numbers [default: picked from the clock and printed]
+  --rng <NAME>          splitmix, or vip for the COSMAC VIP interpreter's routine [default: splitmix]
+  --palette <COLORS>    mono, octo, lcd or amber, or comma separated hex colours: off,on or
+                        off,plane1,plane2,both, optionally followed by a buzzer colour
+                        [default: config.toml or the ROM database's, else mono]
+  --flicker <MODE>      flicker reduction: off, fade[:FRAMES] to let pixels fade out, blend[:FRAMES]
+                        to show pixels lit in any of the last frames, or vblank to hold back
+                        frames that end with a sprite erased [default: off]
+  --mute                start with sound off
+  --volume <0-100>      buzzer volume [default: 25]
+  --tone <HZ>           buzzer frequency [default: 440]
+  --waveform <NAME>     square, sine, triangle or sawtooth [default: square]
+  --config <FILE>       settings file [default: chip8rs/config.toml in the user's config directory]
+  --load-state <FILE>   start from a save state file
+  --record <FILE>       record the keypad every frame to a movie file
+  --play <FILE>         replay a movie recorded on this ROM, its start state, seed and speed are used
+  --rewind <SECONDS>    how far back holding Backspace can rewind, 0 to disable [default: 10]
+  --rewind-interval <N> record a rewind snapshot every N frames [default: 1]
+  --headless            run without a window or sound
+  --debug               start paused with a debugger prompt on the console
+  --frames <N>          stop after N frames
+  --trace <FILE>        log every instruction with the registers it left behind to FILE
+  --trace-format <NAME> text or binary [default: text]
+  --trace-pc <START-END>
+                        only trace instructions in this hex address range
+  --trace-from <FRAME>  only trace from this frame on [default: 0]
+  -h, --help            print this message";
+
+pub const DISASM_USAGE: &str = "\
+Usage: chip8rs disasm [OPTIONS] ROM
+
+Prints a listing of ROM, following jumps and calls from the entry point to tell code from data.
+
+Options:
+  --syntax <NAME>       cowgod or octo [default: cowgod]
+  --platform <NAME>     chip8, schip or xochip, decides which opcodes count as code [default: xochip]
+  --origin <ADDR>       hex address the ROM is loaded at [default: 200]
+  -h, --help            print this message";
+
+pub const ASM_USAGE: &str = "\
+Usage: chip8rs asm SOURCE [-o ROM]
+
+Assembles Cowgod style mnemonics (the disassembler's output) into a ROM.
+
+Options:
+  -o, --output <ROM>    file to write [default: SOURCE with a .ch8 extension]
+  -h, --help            print this message";
+
+pub const TRACE_USAGE: &str = "\
+Usage: chip8rs trace TRACE
+
+Prints a binary trace written by --trace-format binary as text.";
+
+pub struct AsmOptions {
+    pub source: PathBuf,
+    pub output: PathBuf,
+}
+
+pub struct DisasmOptions {
+    pub rom: PathBuf,
+    pub syntax: Syntax,
+    pub platform: Platform,
+    pub origin: u16,
+}
+
+//instructions_per_second, platform, quirks and palette are None unless given, the ROM database or
+//the defaults fill them in once the ROM is known
+pub struct Options {
+    pub rom: Option<PathBuf>,
+    pub instructions_per_second: Option<u32>,
+    pub scale: u32,
+    pub fps: u32,
+    pub platform: Option<Platform>,
+    pub quirks: Option<Quirks>,
+    pub seed: Option<u64>,
+    pub rng: RngMode,
+    pub palette: Option<Palette>,
+    pub flicker: FlickerMode,
+    pub audio: AudioSettings,
+    pub config: Option<PathBuf>,
+    pub load_state: Option<PathBuf>,
+    pub record: Option<PathBuf>,
+    pub play: Option<PathBuf>,
+    pub rewind_seconds: u32,
+    pub rewind_interval: u32,
+    pub headless: bool,
+    pub debug: bool,
+    pub frames: Option<u64>,
+    pub trace: Option<PathBuf>,
+    pub trace_format: TraceFormat,
+    pub trace_filter: TraceFilter,
+}
+
+//Ok(None) means --help was asked for
+pub fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Option<Options>, String> {
+    let mut rom = None;
+    let mut instructions_per_second = None;
+    let mut scale = 10;
+    let mut fps = 60;
+    let mut platform = None;
+    let mut quirks = None;
+    let mut seed = None;
+    let mut rng = RngMode::SplitMix;
+    let mut palette = None;
+    let mut flicker = FlickerMode::Off;
+    let mut audio = AudioSettings::default();
+    let mut config = None;
+    let mut load_state = None;
+    let mut record = None;
+    let mut play = None;
+    let mut rewind_seconds = 10;
+    let mut rewind_interval = 1;
+    let mut headless = false;
+    let mut debug = false;
+    let mut frames = None;
+    let mut trace = None;
+    let mut trace_format = TraceFormat::Text;
+    let mut trace_filter = TraceFilter::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--ips" | "--hz" => instructions_per_second = Some(value(&arg, &mut args)?),
+            "--scale" => scale = value(&arg, &mut args)?,
+            "--fps" => fps = value(&arg, &mut args)?,
+            "--platform" => {
+                let name: String = value(&arg, &mut args)?;
+                platform = Some(Platform::from_name(&name).ok_or(format!("unknown platform '{}'", name))?);
+            }
+            "--quirks" => {
+                let name: String = value(&arg, &mut args)?;
+                quirks = Some(Quirks::from_preset(&name).ok_or(format!("unknown quirks preset '{}'", name))?);
+            }
+            "--seed" => seed = Some(value(&arg, &mut args)?),
+            "--rng" => {
+                let name: String = value(&arg, &mut args)?;
+                rng = RngMode::from_name(&name).ok_or(format!("unknown random number generator '{}'", name))?;
+            }
+            "--palette" => {
+                let spec: String = value(&arg, &mut args)?;
+                palette = Some(Palette::parse(&spec).ok_or(format!("invalid palette '{}'", spec))?);
+            }
+            "--flicker" => {
+                let name: String = value(&arg, &mut args)?;
+                flicker = FlickerMode::from_name(&name).ok_or(format!("unknown flicker reduction mode '{}'", name))?;
+            }
+            "--mute" => audio.muted = true,
+            "--volume" => {
+                let volume: u32 = value(&arg, &mut args)?;
+                audio.volume = volume.min(100) as f32 / 100.0;
+            }
+            "--tone" => audio.frequency = value(&arg, &mut args)?,
+            "--waveform" => {
+                let name: String = value(&arg, &mut args)?;
+                audio.waveform = Waveform::from_name(&name).ok_or(format!("unknown waveform '{}'", name))?;
+            }
+            "--config" => config = Some(value(&arg, &mut args)?),
+            "--load-state" => load_state = Some(value(&arg, &mut args)?),
+            "--record" => record = Some(value(&arg, &mut args)?),
+            "--play" => play = Some(value(&arg, &mut args)?),
+            "--rewind" => rewind_seconds = value(&arg, &mut args)?,
+            "--rewind-interval" => rewind_interval = value(&arg, &mut args)?,
+            "--headless" => headless = true,
+            "--debug" => debug = true,
+            "--frames" => frames = Some(value(&arg, &mut args)?),
+            "--trace" => trace = Some(value(&arg, &mut args)?),
+            "--trace-format" => {
+                let name: String = value(&arg, &mut args)?;
+                trace_format = TraceFormat::from_name(&name).ok_or(format!("unknown trace format '{}'", name))?;
+            }
+            "--trace-pc" => {
+                let range: String = value(&arg, &mut args)?;
+                let parse = |address: &str| u16::from_str_radix(address.trim_start_matches("0x"), 16).ok();
+                let bounds = range.split_once('-').and_then(|(start, end)| parse(start).zip(parse(end)));
+                trace_filter.pc_range = Some(bounds.filter(|(start, end)| start <= end).ok_or(format!("invalid address range '{}'", range))?);
+            }
+            "--trace-from" => trace_filter.from_frame = value(&arg, &mut args)?,
+            flag if flag.starts_with('-') => return Err(format!("unknown option '{}'", flag)),
+            path => {
+                if rom.is_some() {
+                    return Err(format!("unexpected argument '{}'", path));
+                }
+                rom = Some(PathBuf::from(path));
+            }
+        }
+    }
+
+    if scale == 0 || instructions_per_second == Some(0) || !(1..=60).contains(&fps) {
+        return Err("--ips and --scale must be above 0 and --fps between 1 and 60".to_string());
+    }
+    if debug && headless {
+        return Err("--debug needs a window, it can't be used with --headless".to_string());
+    }
+    if record.is_some() && play.is_some() {
+        return Err("--record and --play can't be used together".to_string());
+    }
+    if play.is_some() && load_state.is_some() {
+        return Err("--play starts from the movie's own state, it can't be used with --load-state".to_string());
+    }
+    if debug && (record.is_some() || play.is_some()) {
+        return Err("--record and --play can't be used with --debug".to_string());
+    }
+    Ok(Some(Options { rom, instructions_per_second, scale, fps, platform, quirks, seed, rng, palette, flicker, audio, config,
+        load_state, record, play, rewind_seconds, rewind_interval, headless, debug, frames, trace, trace_format, trace_filter }))
+}
+
+//arguments after 'asm', Ok(None) means --help was asked for
+pub fn parse_asm_args<I: Iterator<Item = String>>(mut args: I) -> Result<Option<AsmOptions>, String> {
+    let mut source: Option<PathBuf> = None;
+    let mut output = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-o" | "--output" => output = Some(value(&arg, &mut args)?),
+            flag if flag.starts_with('-') => return Err(format!("unknown option '{}'", flag)),
+            path => {
+                if source.is_some() {
+                    return Err(format!("unexpected argument '{}'", path));
+                }
+                source = Some(PathBuf::from(path));
+            }
+        }
+    }
+    let source = source.ok_or("no source file given")?;
+    let output = output.unwrap_or_else(|| source.with_extension("ch8"));
+    Ok(Some(AsmOptions { source, output }))
+}
+
+//argument after 'trace', Ok(None) means --help was asked for
+pub fn parse_trace_args<I: Iterator<Item = String>>(mut args: I) -> Result<Option<PathBuf>, String> {
+    match (args.next(), args.next()) {
+        (Some(flag), _) if flag == "-h" || flag == "--help" => Ok(None),
+        (Some(flag), _) if flag.starts_with('-') => Err(format!("unknown option '{}'", flag)),
+        (Some(path), None) => Ok(Some(PathBuf::from(path))),
+        (Some(_), Some(extra)) => Err(format!("unexpected argument '{}'", extra)),
+        (None, _) => Err("no trace file given".to_string())
+    }
+}
+
+//arguments after 'disasm', Ok(None) means --help was asked for
+pub fn parse_disasm_args<I: Iterator<Item = String>>(mut args: I) -> Result<Option<DisasmOptions>, String> {
+    let mut rom = None;
+    let mut syntax = Syntax::Cowgod;
+    let mut platform = Platform::XoChip;
+    let mut origin = 0x200;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--syntax" => {
+                let name: String = value(&arg, &mut args)?;
+                syntax = Syntax::from_name(&name).ok_or(format!("unknown syntax '{}'", name))?;
+            }
+            "--platform" => {
+                let name: String = value(&arg, &mut args)?;
+                platform = Platform::from_name(&name).ok_or(format!("unknown platform '{}'", name))?;
+            }
+            "--origin" => {
+                let address: String = value(&arg, &mut args)?;
+                origin = u16::from_str_radix(address.trim_start_matches("0x"), 16).map_err(|_| format!("invalid origin '{}'", address))?;
+            }
+            flag if flag.starts_with('-') => return Err(format!("unknown option '{}'", flag)),
+            path => {
+                if rom.is_some() {
+                    return Err(format!("unexpected argument '{}'", path));
+                }
+                rom = Some(PathBuf::from(path));
+            }
+        }
+    }
+    let rom = rom.ok_or("no ROM given")?;
+    Ok(Some(DisasmOptions { rom, syntax, platform, origin }))
+}
+
+fn value<T: FromStr, I: Iterator<Item = String>>(flag: &str, args: &mut I) -> Result<T, String> {
+    let raw = args.next().ok_or(format!("{} needs a value", flag))?;
+    raw.parse().map_err(|_| format!("invalid value '{}' for {}", raw, flag))
+}
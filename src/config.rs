@@ -0,0 +1,263 @@
+use std::{env, fs};
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+use crate::display::Palette;
+use crate::keymap::{parse_chip8_key, Binding, Keymap};
+use crate::romdb::RomInfo;
+
+//settings read from config.toml. the top level applies to every ROM, a [rom.<sha1>] table applies on
+//top of it for that ROM only:
+//
+//  palette = "amber"
+//
+//  [keys]
+//  5 = ["W", "Up"]
+//  C = "scancode:Z"
+//
+//  [gamepad]
+//  deadzone = 0.3
+//  5 = ["a", "b"]
+//
+//  [rom.0123...cdef.keys]
+//  4 = "Left"
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    pub keymap: Keymap,
+    pub palette: Option<Palette>,
+}
+
+impl Config {
+    //reads `path`, or <config dir>/chip8rs/config.toml when none is given. only a missing default
+    //file is fine, everything then keeps its default. the ROM database's controls and colours go
+    //between the top level and the ROM's own table
+    pub fn load(path: Option<&Path>, rom_hash: &str, rom_info: &RomInfo) -> Result<Config, String> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => (default_path(), false)
+        };
+        let text = match required || path.exists() {
+            true => fs::read_to_string(&path).map_err(|error| format!("could not read {}: {}", path.display(), error))?,
+            false => String::new()
+        };
+        Config::parse(&text, rom_hash, rom_info).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    pub fn parse(text: &str, rom_hash: &str, rom_info: &RomInfo) -> Result<Config, String> {
+        let table: Table = text.parse().map_err(|error: toml::de::Error| error.to_string())?;
+        let mut config = Config::default();
+        config.apply(&table, "")?;
+        config.keymap.apply_controls(&rom_info.keys);
+        config.palette = rom_info.palette.or(config.palette);
+        if let Some(roms) = table.get("rom") {
+            let roms = roms.as_table().ok_or("'rom' should be a table of ROM SHA-1s")?;
+            let overrides = roms.iter().find(|(hash, _)| hash.eq_ignore_ascii_case(rom_hash));
+            if let Some((hash, overrides)) = overrides {
+                let overrides = overrides.as_table().ok_or(format!("'rom.{}' should be a table", hash))?;
+                config.apply(overrides, &format!("rom.{}.", hash))?;
+            }
+        }
+        Ok(config)
+    }
+
+    //`prefix` names the table in errors
+    fn apply(&mut self, table: &Table, prefix: &str) -> Result<(), String> {
+        for (name, value) in table {
+            match name.as_str() {
+                "palette" => self.palette = Some(parse_palette(value)
+                    .ok_or(format!("'{}palette' should be a theme name or a list of 2 to 5 hex colours", prefix))?),
+                "keys" => {
+                    let table = value.as_table().ok_or(format!("'{}keys' should be a table", prefix))?;
+                    apply_bindings(&mut self.keymap, table, &format!("{}keys", prefix), false)?;
+                }
+                "gamepad" => {
+                    let mut table = value.as_table().ok_or(format!("'{}gamepad' should be a table", prefix))?.clone();
+                    if let Some(deadzone) = table.remove("deadzone") {
+                        //TOML keeps integers apart from floats, `deadzone = 0` is as good as 0.0
+                        let deadzone = deadzone.as_float().or(deadzone.as_integer().map(|deadzone| deadzone as f64))
+                            .filter(|deadzone| (0.0..1.0).contains(deadzone))
+                            .ok_or(format!("'{}gamepad.deadzone' should be a number from 0.0 up to 1.0", prefix))?;
+                        self.keymap.deadzone = (deadzone * i16::MAX as f64) as i16;
+                    }
+                    apply_bindings(&mut self.keymap, &table, &format!("{}gamepad", prefix), true)?;
+                }
+                "rom" if prefix.is_empty() => {}
+                _ => return Err(format!("unknown setting '{}{}'", prefix, name))
+            }
+        }
+        Ok(())
+    }
+}
+
+//each CHIP-8 key listed replaces its keyboard bindings ([keys]) or gamepad bindings ([gamepad]), an
+//empty list unbinds it. `table_name` is used in errors
+fn apply_bindings(keymap: &mut Keymap, table: &Table, table_name: &str, gamepad: bool) -> Result<(), String> {
+    let parse = if gamepad { Binding::parse_gamepad } else { Binding::parse_key };
+    for (name, value) in table {
+        let key = parse_chip8_key(name).ok_or(format!("unknown CHIP-8 key '{}' in '{}', expected 0-F", name, table_name))?;
+        let names = match value {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().map(Value::as_str).collect::<Option<_>>()
+                .ok_or(format!("'{}.{}' should only hold names", table_name, name))?,
+            _ => return Err(format!("'{}.{}' should be a name or a list of them", table_name, name))
+        };
+        let bindings = names.into_iter()
+            .map(|binding| parse(binding).ok_or(format!("unknown {} '{}' for '{}.{}'",
+                if gamepad { "gamepad input" } else { "key" }, binding, table_name, name)))
+            .collect::<Result<_, _>>()?;
+        keymap.set_bindings(key, gamepad, bindings);
+    }
+    Ok(())
+}
+
+//a theme name or hex colours, as a list or like --palette
+pub fn parse_palette(value: &Value) -> Option<Palette> {
+    match value {
+        Value::String(spec) => Palette::parse(spec),
+        Value::Array(colors) => Palette::parse(&colors.iter().map(Value::as_str).collect::<Option<Vec<_>>>()?.join(",")),
+        _ => None
+    }
+}
+
+pub fn default_path() -> PathBuf {
+    config_dir().join("chip8rs").join("config.toml")
+}
+
+pub fn config_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME").or_else(|| env::var_os("APPDATA")) {
+        return PathBuf::from(dir);
+    }
+    match env::var_os("HOME") {
+        Some(home) => PathBuf::from(home).join(".config"),
+        None => PathBuf::from(".")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use sdl2::controller::{Axis, Button};
+    use sdl2::keyboard::{Keycode, Scancode};
+    use crate::keymap::Control;
+    use super::*;
+
+    const HASH: &str = "0123456789abcdef0123456789abcdef01234567";
+
+    fn parse(text: &str) -> Result<Config, String> {
+        Config::parse(text, HASH, &RomInfo::default())
+    }
+    fn keys(config: &Config, binding: Binding) -> Vec<usize> {
+        config.keymap.keys_bound_to(binding).collect()
+    }
+
+    #[test]
+    fn empty_file_keeps_the_defaults() {
+        let config = parse("").unwrap();
+        assert_eq!(config.keymap, Keymap::default());
+        assert_eq!(config.palette, None);
+    }
+
+    #[test]
+    fn keys_take_a_name_or_a_list() {
+        let config = parse("
+            [keys]
+            5 = [\"I\", \"Up\"]
+            c = \"scancode:Z\"
+            0 = []
+        ").unwrap();
+        assert_eq!(keys(&config, Binding::Key(Keycode::I)), [0x5]);
+        assert_eq!(keys(&config, Binding::Key(Keycode::Up)), [0x5]);
+        assert_eq!(keys(&config, Binding::Key(Keycode::W)), Vec::<usize>::new());
+        assert_eq!(keys(&config, Binding::Scan(Scancode::Z)), [0xC]);
+        assert_eq!(keys(&config, Binding::Key(Keycode::Num1)), Vec::<usize>::new());
+        //keys left out keep their defaults, and so do the gamepad bindings of the ones listed
+        assert_eq!(keys(&config, Binding::Key(Keycode::Q)), [0x4]);
+        assert!(config.keymap.is_pressed(0x5, &HashSet::from([Binding::Button(Button::A)])));
+    }
+
+    #[test]
+    fn rom_tables_override_the_top_level() {
+        let text = format!("
+            [keys]
+            4 = \"J\"
+            [rom.{}.keys]
+            4 = \"Left\"
+            [rom.ffffffffffffffffffffffffffffffffffffffff.keys]
+            4 = \"K\"
+        ", HASH.to_uppercase());
+        let config = parse(&text).unwrap();
+        assert_eq!(keys(&config, Binding::Key(Keycode::Left)), [0x4]);
+        assert_eq!(keys(&config, Binding::Key(Keycode::J)), Vec::<usize>::new());
+        assert_eq!(keys(&config, Binding::Key(Keycode::K)), Vec::<usize>::new());
+        //another ROM only gets the top level
+        let config = Config::parse(&text, "ffffffffffffffffffffffffffffffffffffffff", &RomInfo::default()).unwrap();
+        assert_eq!(keys(&config, Binding::Key(Keycode::K)), [0x4]);
+        assert_eq!(keys(&config, Binding::Key(Keycode::J)), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn rom_database_controls_go_between_the_two() {
+        let rom_info = RomInfo { keys: vec![(Control::Up, 0x5)], ..RomInfo::default() };
+        let config = Config::parse("[keys]\n2 = \"Up\"", HASH, &rom_info).unwrap();
+        assert_eq!(keys(&config, Binding::Key(Keycode::Up)), [0x5]);
+        let text = format!("[rom.{}.keys]\n2 = \"Up\"", HASH);
+        let config = Config::parse(&text, HASH, &rom_info).unwrap();
+        assert_eq!(keys(&config, Binding::Key(Keycode::Up)), [0x2, 0x5]);
+    }
+
+    #[test]
+    fn gamepad_buttons_sticks_and_deadzone() {
+        let config = parse("
+            [gamepad]
+            deadzone = 0.5
+            5 = [\"x\", \"rightx+\"]
+            4 = \"lefty-\"
+        ").unwrap();
+        assert_eq!(config.keymap.deadzone, i16::MAX / 2);
+        assert_eq!(keys(&config, Binding::Button(Button::X)), [0x5]);
+        assert_eq!(keys(&config, Binding::Axis(Axis::RightX, true)), [0x5]);
+        assert_eq!(keys(&config, Binding::Axis(Axis::LeftY, false)), [0x2, 0x4]);
+        //the keyboard bindings stay
+        assert_eq!(keys(&config, Binding::Key(Keycode::W)), [0x5]);
+        assert_eq!(keys(&config, Binding::Button(Button::A)), Vec::<usize>::new());
+        assert_eq!(parse("[gamepad]\ndeadzone = 0").unwrap().keymap.deadzone, 0);
+        for deadzone in ["1", "1.0", "-0.1", "\"0.3\""] {
+            assert_eq!(parse(&format!("[gamepad]\ndeadzone = {}", deadzone)).unwrap_err(),
+                "'gamepad.deadzone' should be a number from 0.0 up to 1.0");
+        }
+        assert_eq!(parse("[gamepad]\n1 = \"Q\"").unwrap_err(), "unknown gamepad input 'Q' for 'gamepad.1'");
+    }
+
+    #[test]
+    fn palettes() {
+        assert_eq!(parse("palette = \"amber\"").unwrap().palette, Palette::parse("amber"));
+        assert_eq!(parse("palette = [\"#000000\", \"ffffff\"]").unwrap().palette, Palette::parse("#000000,#ffffff"));
+        assert_eq!(parse("palette = \"#000000,#ff0000,#00ff00,#0000ff\"").unwrap().palette,
+            Palette::parse("#000000,#ff0000,#00ff00,#0000ff"));
+        for palette in ["\"plaid\"", "[\"#000000\"]", "[\"#000000\", 16777215]", "1"] {
+            assert_eq!(parse(&format!("palette = {}", palette)).unwrap_err(),
+                "'palette' should be a theme name or a list of 2 to 5 hex colours");
+        }
+        //the ROM database's colours beat the top level, the ROM's own table beats both
+        let rom_info = RomInfo { palette: Palette::parse("lcd"), ..RomInfo::default() };
+        assert_eq!(Config::parse("palette = \"amber\"", HASH, &rom_info).unwrap().palette, Palette::parse("lcd"));
+        let text = format!("palette = \"amber\"\n[rom.{}]\npalette = \"octo\"", HASH);
+        assert_eq!(Config::parse(&text, HASH, &rom_info).unwrap().palette, Palette::parse("octo"));
+    }
+
+    #[test]
+    fn bad_settings_are_named() {
+        for (text, error) in [
+            ("[keys]\nG = \"Q\"", "unknown CHIP-8 key 'G' in 'keys', expected 0-F"),
+            ("[keys]\n1 = \"NoSuchKey\"", "unknown key 'NoSuchKey' for 'keys.1'"),
+            ("[keys]\n1 = [\"Q\", 2]", "'keys.1' should only hold names"),
+            ("[keys]\n1 = 2", "'keys.1' should be a name or a list of them"),
+            ("keys = 1", "'keys' should be a table"),
+            ("speed = 1", "unknown setting 'speed'"),
+            (&format!("[rom.{}]\nrom = 1", HASH), &format!("unknown setting 'rom.{}.rom'", HASH)),
+            ("rom = 1", "'rom' should be a table of ROM SHA-1s"),
+        ] {
+            assert_eq!(parse(text).unwrap_err(), error);
+        }
+        assert!(parse("[keys").is_err());
+    }
+}
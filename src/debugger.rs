@@ -0,0 +1,306 @@
+use std::{io, thread};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use chip8rs_core::{disasm, Chip8Error, Debugger, Processor};
+use chip8rs_core::bus::{Access, Watchpoint};
+use chip8rs_core::debug::{Breakpoint, Condition, StopReason};
+
+const HELP: &str = "\
+Debugger commands (addresses are hex, condition values decimal or 0x hex):
+  c, continue           resume running
+  p, pause              stop before the next instruction (or press F12 in the window)
+  s, step [N]           run N instructions [default: 1]
+  n, next               step, running 2nnn calls until they return
+  o, out                run until the current subroutine returns
+  b, break ADDR [if COND]
+                        break at ADDR, optionally only when COND holds, e.g. 'b 2A4 if V3 == 5'
+  d, delete ADDR        remove the breakpoint at ADDR
+  bl, breakpoints       list breakpoints
+  w, watch ADDR[-END] [rwx] [log]
+                        watch reads, writes and/or executes of a range [default: rw]. stops just
+                        after the instruction that made the access, or only prints it with 'log'
+  dw ADDR               remove the watchpoint starting at ADDR
+  wl, watchpoints       list watchpoints
+  r, regs               show registers, stack, timers and the code around PC
+  l, list [ADDR]        disassemble around ADDR [default: PC]
+  x ADDR [LEN]          dump LEN bytes of memory [default: 16]
+  h, help               print this message";
+
+//console REPL for --debug. stdin is read on its own thread so the window keeps running while paused
+pub struct DebugConsole {
+    debugger: Debugger,
+    commands: Receiver<String>,
+}
+
+impl DebugConsole {
+    pub fn new(chip: &Processor) -> DebugConsole {
+        let (sender, commands) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        println!("{}\n", HELP);
+        print_state(chip);
+        DebugConsole { debugger: Debugger::new(), commands }
+    }
+
+    pub fn pause(&mut self, chip: &Processor) {
+        if !self.debugger.is_paused() {
+            self.debugger.pause();
+            print_state(chip);
+        }
+    }
+
+    //runs every command typed since the last call
+    pub fn poll(&mut self, chip: &mut Processor) {
+        loop {
+            match self.commands.try_recv() {
+                Ok(line) => self.execute(chip, &line),
+                Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => return
+            }
+        }
+    }
+
+    //true when a whole frame ran. faults pause the debugger instead of ending the emulator
+    pub fn run_frame(&mut self, chip: &mut Processor, instructions_per_frame: u32) -> bool {
+        if self.debugger.is_paused() {
+            return false;
+        }
+        let result = self.debugger.run_frame(chip, instructions_per_frame);
+        self.print_hits();
+        match result {
+            Ok(None) => true,
+            Ok(Some(StopReason::Breakpoint(address))) => {
+                println!("Breakpoint at 0x{:03X}", address);
+                print_state(chip);
+                false
+            }
+            Ok(Some(StopReason::Returned)) | Ok(Some(StopReason::Watchpoint(_))) => {
+                print_state(chip);
+                false
+            }
+            Err(error) => {
+                report_fault(chip, error);
+                false
+            }
+        }
+    }
+
+    fn print_hits(&mut self) {
+        for hit in self.debugger.take_hits() {
+            let access = match hit.access {
+                Access::Read => "read",
+                Access::Write => "write",
+                Access::Execute => "execute",
+            };
+            println!("Watch: {} 0x{:03X} = {:02X} by 0x{:03X}", access, hit.address, hit.value, hit.pc);
+        }
+    }
+
+    fn execute(&mut self, chip: &mut Processor, line: &str) {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some(&command) = words.first() else { return };
+        match (command, &words[1..]) {
+            ("c" | "continue", []) => self.debugger.resume(),
+            ("p" | "pause", []) => self.pause(chip),
+            ("s" | "step", args) => {
+                let count = match args {
+                    [] => Some(1),
+                    [count] => count.parse().ok(),
+                    _ => None
+                };
+                let Some(count) = count else {
+                    println!("usage: step [N]");
+                    return;
+                };
+                for _ in 0..count {
+                    let result = self.debugger.step(chip);
+                    self.print_hits();
+                    if let Err(error) = result {
+                        report_fault(chip, error);
+                        return;
+                    }
+                }
+                print_state(chip);
+            }
+            ("n" | "next", []) => {
+                let result = self.debugger.step_over(chip);
+                self.print_hits();
+                match result {
+                    Ok(()) if self.debugger.is_paused() => print_state(chip),
+                    Ok(()) => {}
+                    Err(error) => report_fault(chip, error)
+                }
+            }
+            ("o" | "out", []) => {
+                if !self.debugger.step_out(chip) {
+                    println!("Not inside a subroutine");
+                }
+            }
+            ("b" | "break", [address, rest @ ..]) => {
+                let Some(address) = parse_address(address) else {
+                    println!("invalid address '{}'", address);
+                    return;
+                };
+                let condition = match rest {
+                    [] => None,
+                    ["if", condition @ ..] => match Condition::parse(&condition.join(" ")) {
+                        Some(condition) => Some(condition),
+                        None => {
+                            println!("invalid condition '{}'", condition.join(" "));
+                            return;
+                        }
+                    },
+                    _ => {
+                        println!("usage: break ADDR [if COND]");
+                        return;
+                    }
+                };
+                self.debugger.add_breakpoint(Breakpoint { address, condition });
+                println!("Breakpoint set at 0x{:03X}", address);
+            }
+            ("d" | "delete", [address]) => match parse_address(address) {
+                Some(address) if self.debugger.remove_breakpoint(address) => println!("Removed breakpoint at 0x{:03X}", address),
+                Some(address) => println!("No breakpoint at 0x{:03X}", address),
+                None => println!("invalid address '{}'", address)
+            },
+            ("bl" | "breakpoints", []) => {
+                if self.debugger.breakpoints().is_empty() {
+                    println!("No breakpoints");
+                }
+                for breakpoint in self.debugger.breakpoints() {
+                    match breakpoint.condition {
+                        Some(condition) => println!("  0x{:03X} if {}", breakpoint.address, condition),
+                        None => println!("  0x{:03X}", breakpoint.address)
+                    }
+                }
+            }
+            ("w" | "watch", [range, flags @ ..]) => match parse_watchpoint(range, flags) {
+                Some(watchpoint) => {
+                    chip.get_bus_mut().add_watchpoint(watchpoint);
+                    println!("Watching {}", describe_watchpoint(&watchpoint));
+                }
+                None => println!("usage: watch ADDR[-END] [rwx] [log]")
+            },
+            ("dw", [address]) => match parse_address(address) {
+                Some(address) if chip.get_bus_mut().remove_watchpoint(address) => println!("Removed watchpoint at 0x{:03X}", address),
+                Some(address) => println!("No watchpoint starts at 0x{:03X}", address),
+                None => println!("invalid address '{}'", address)
+            },
+            ("wl" | "watchpoints", []) => {
+                if chip.get_bus().watchpoints().is_empty() {
+                    println!("No watchpoints");
+                }
+                for watchpoint in chip.get_bus().watchpoints() {
+                    println!("  {}", describe_watchpoint(watchpoint));
+                }
+            }
+            ("r" | "regs", []) => print_state(chip),
+            ("l" | "list", args) => {
+                let address = match args {
+                    [] => Some(chip.get_program_counter()),
+                    [address] => parse_address(address),
+                    _ => None
+                };
+                match address {
+                    Some(address) => print_disassembly(chip, address),
+                    None => println!("usage: list [ADDR]")
+                }
+            }
+            ("x", args) => {
+                let range = match args {
+                    [address] => parse_address(address).map(|address| (address, 16)),
+                    [address, length] => parse_address(address).zip(length.parse().ok()),
+                    _ => None
+                };
+                match range {
+                    Some((address, length)) => print_memory(chip, address as usize, length),
+                    None => println!("usage: x ADDR [LEN]")
+                }
+            }
+            ("h" | "help", []) => println!("{}", HELP),
+            _ => println!("unknown command '{}', 'help' lists them", line.trim())
+        }
+    }
+}
+
+//"2F0-2FF rw log"
+fn parse_watchpoint(range: &str, flags: &[&str]) -> Option<Watchpoint> {
+    let (start, end) = match range.split_once('-') {
+        Some((start, end)) => (parse_address(start)?, parse_address(end)?),
+        None => (parse_address(range)?, parse_address(range)?)
+    };
+    if end < start {
+        return None;
+    }
+    let mut watchpoint = Watchpoint { start, end, read: true, write: true, execute: false, pause: true };
+    for flag in flags {
+        match *flag {
+            "log" => watchpoint.pause = false,
+            kinds if !kinds.is_empty() && kinds.chars().all(|kind| "rwx".contains(kind)) => {
+                watchpoint.read = kinds.contains('r');
+                watchpoint.write = kinds.contains('w');
+                watchpoint.execute = kinds.contains('x');
+            }
+            _ => return None
+        }
+    }
+    Some(watchpoint)
+}
+
+fn describe_watchpoint(watchpoint: &Watchpoint) -> String {
+    let kinds: String = [(watchpoint.read, 'r'), (watchpoint.write, 'w'), (watchpoint.execute, 'x')].iter()
+        .filter(|(enabled, _)| *enabled).map(|(_, kind)| *kind).collect();
+    let action = if watchpoint.pause { "" } else { " log" };
+    format!("0x{:03X}-0x{:03X} {}{}", watchpoint.start, watchpoint.end, kinds, action)
+}
+
+fn report_fault(chip: &Processor, error: Chip8Error) {
+    println!("Fault: {}", error);
+    print_state(chip);
+}
+
+fn print_state(chip: &Processor) {
+    let (delay, sound) = chip.get_timers();
+    println!("PC 0x{:03X}  I 0x{:03X}  SP {}  DT {}  ST {}", chip.get_program_counter(), chip.get_index_register(),
+        chip.get_stack_pointer(), delay, sound);
+    let registers = chip.get_registers();
+    for (row, values) in registers.chunks(8).enumerate() {
+        let line: Vec<String> = values.iter().enumerate().map(|(idx, value)| format!("V{:X} {:02X}", row * 8 + idx, value)).collect();
+        println!("{}", line.join("  "));
+    }
+    let stack: Vec<String> = chip.get_stack().iter().map(|address| format!("0x{:03X}", address)).collect();
+    println!("Stack [{}]", stack.join(", "));
+    print_disassembly(chip, chip.get_program_counter());
+}
+
+//three instructions either side of address
+fn print_disassembly(chip: &Processor, address: u16) {
+    let pc = chip.get_program_counter();
+    let start = address.saturating_sub(6);
+    for offset in 0..7 {
+        let current = start.wrapping_add(offset * 2);
+        if current as usize + 1 >= chip.get_memory().len() {
+            break;
+        }
+        let opcode = chip.peek_word(current);
+        let marker = if current == pc { ">" } else { " " };
+        println!("{} 0x{:03X}  {:04X}  {}", marker, current, opcode, disasm::mnemonic(opcode, chip.peek_word(current.wrapping_add(2))));
+    }
+}
+
+fn print_memory(chip: &Processor, address: usize, length: usize) {
+    let memory = chip.get_memory();
+    let end = (address + length).min(memory.len());
+    for (row, bytes) in memory.get(address..end).unwrap_or(&[]).chunks(16).enumerate() {
+        let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+        println!("0x{:03X}  {}", address + row * 16, hex.join(" "));
+    }
+}
+
+fn parse_address(text: &str) -> Option<u16> {
+    u16::from_str_radix(text.trim_start_matches("0x"), 16).ok()
+}
@@ -1,90 +1,284 @@
-
 use sdl2::pixels::Color;
 use sdl2::event::Event;
-use sdl2::keyboard::{Keycode};
+use std::collections::HashSet;
+use sdl2::keyboard::{Keycode, Mod, Scancode};
 use sdl2::rect::{Rect};
 use sdl2::render::Canvas;
-use std::sync::{Mutex, Arc, MutexGuard};
-use std::thread;
-use std::time::Duration;
+use sdl2::{EventPump, GameControllerSubsystem, Sdl};
+use sdl2::controller::GameController;
 use sdl2::video::Window;
+use chip8rs_core::{Processor, HIRES_HEIGHT, HIRES_WIDTH, LORES_HEIGHT, LORES_WIDTH};
+use crate::flicker::Phosphor;
+use crate::keymap::{Binding, Keymap};
+
+//colours indexed by the XO-CHIP plane mask: off, plane 1, plane 2, both planes. with a buzzer colour
+//the background takes it while the sound timer runs
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Palette {
+    pub colors: [Color; 4],
+    pub buzzer: Option<Color>,
+}
 
+//named palettes for --palette and F11, the first is the default
+pub const THEMES: [(&str, Palette); 4] = [
+    ("mono", Palette { colors: [Color::RGB(0, 0, 0), Color::RGB(255, 255, 255), Color::RGB(170, 170, 170), Color::RGB(85, 85, 85)], buzzer: None }),
+    //Octo's default colours
+    ("octo", Palette { colors: [Color::RGB(0x99, 0x66, 0x00), Color::RGB(0xFF, 0xCC, 0x00), Color::RGB(0xFF, 0x66, 0x00), Color::RGB(0x66, 0x22, 0x00)],
+        buzzer: Some(Color::RGB(0xFF, 0xAA, 0x00)) }),
+    ("lcd", Palette { colors: [Color::RGB(0x9B, 0xBC, 0x0F), Color::RGB(0x0F, 0x38, 0x0F), Color::RGB(0x8B, 0xAC, 0x0F), Color::RGB(0x30, 0x62, 0x30)], buzzer: None }),
+    ("amber", Palette { colors: [Color::RGB(0x14, 0x0C, 0x00), Color::RGB(0xFF, 0xB0, 0x00), Color::RGB(0xB3, 0x7B, 0x00), Color::RGB(0x66, 0x46, 0x00)], buzzer: None }),
+];
 
+impl Palette {
+    //a theme name, or "#rrggbb,#rrggbb" (off, on) or four colours for XO-CHIP, either optionally
+    //followed by the buzzer colour. the '#' is optional
+    pub fn parse(spec: &str) -> Option<Palette> {
+        if let Some((_, palette)) = THEMES.iter().find(|(name, _)| name.eq_ignore_ascii_case(spec.trim())) {
+            return Some(*palette);
+        }
+        let colors: Vec<Color> = spec.split(',').map(parse_hex_color).collect::<Option<_>>()?;
+        let (colors, buzzer) = match colors[..] {
+            [off, on] => ([off, on, on, on], None),
+            [off, on, buzzer] => ([off, on, on, on], Some(buzzer)),
+            [off, plane1, plane2, both] => ([off, plane1, plane2, both], None),
+            [off, plane1, plane2, both, buzzer] => ([off, plane1, plane2, both], Some(buzzer)),
+            _ => return None
+        };
+        Some(Palette { colors, buzzer })
+    }
+}
 
+impl Default for Palette {
+    fn default() -> Palette {
+        THEMES[0].1
+    }
+}
 
-pub fn start_display(scale_factor: u32,  delay: f32, key_mapping: [Keycode; 16], vm_lock: Arc<Mutex<[[u8; 64]; 32]>>, kp_lock: Arc<Mutex<[u8; 16]>>) {
-        thread::spawn(move || {
-        let sdl_context = sdl2::init().unwrap();
+fn parse_hex_color(hex: &str) -> Option<Color> {
+    let hex = hex.trim();
+    let hex = hex.strip_prefix('#').unwrap_or(hex);
+    //from_str_radix would also take a sign
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let rgb = u32::from_str_radix(hex, 16).ok()?;
+    Some(Color::RGB((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+}
+
+//emulator controls handled by the main loop rather than the keypad
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hotkey {
+    Quit,
+    //F1-F10 load a save slot, shift+F1-F10 saves to it
+    SaveSlot(u8),
+    LoadSlot(u8),
+    //F12, breaks into the debugger when running with --debug
+    DebugBreak,
+    //shift+F11, steps through the flicker reduction modes
+    FlickerMode,
+}
+
+const SLOT_KEYS: [Keycode; 10] = [
+    Keycode::F1, Keycode::F2, Keycode::F3, Keycode::F4, Keycode::F5,
+    Keycode::F6, Keycode::F7, Keycode::F8, Keycode::F9, Keycode::F10
+];
+
+pub struct Display {
+    canvas: Canvas<Window>,
+    event_pump: EventPump,
+    //window size in pixels
+    width: u32,
+    height: u32,
+    keymap: Keymap,
+    //keyboard keys currently down, by keycode and by scancode, and gamepad inputs
+    held: HashSet<Binding>,
+    //None when SDL couldn't start its game controller support
+    controller_subsystem: Option<GameControllerSubsystem>,
+    //SDL only reports input from controllers that are open
+    controllers: Vec<GameController>,
+    //the palette the ROM started with followed by the themes, F11 steps through them
+    palettes: Vec<Palette>,
+    palette_index: usize,
+}
+
+impl Display {
+    pub fn new(sdl_context: &Sdl, scale_factor: u32, keymap: Keymap, palette: Palette) -> Display {
         let video_subsystem = sdl_context.video().unwrap();
-        let window = video_subsystem.window("Chip8", 64*scale_factor, 32*scale_factor)
+        let (width, height) = window_size(scale_factor);
+        let window = video_subsystem.window("Chip8", width, height)
         .position_centered().opengl().build().unwrap();
 
         let mut canvas = window.into_canvas().build().unwrap();
         canvas.set_draw_color(Color::BLACK);
         canvas.clear();
-        let mut event_pump = sdl_context.event_pump().unwrap();
         canvas.present();
-            loop {
-                thread::sleep(Duration::from_secs_f32(delay));
-                update(&mut canvas, scale_factor, &vm_lock);
-                for event in event_pump.poll_iter() {
-                    match event {
-                        Event::Quit {..} | Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
-                            panic!("Window quit")
-                        }
-                        Event::KeyDown { keycode, .. } => {
-                            let mut keypad = kp_lock.lock().unwrap();
-                            let key = keycode.unwrap();
-
-                            let index = key_mapping.iter().position(|x| x.to_owned() == key);
-                            if index.is_some(){
-                                let idx = index.unwrap();
-                                keypad[idx] = 1;
-                            }
-                        }
-                        Event::KeyUp { keycode, .. } => {
-                            let mut keypad = kp_lock.lock().unwrap();
-                            let key = keycode.unwrap();
-
-                            let index = key_mapping.iter().position(|x| x.to_owned() == key);
-                            if index.is_some(){
-                                let idx = index.unwrap();
-                                keypad[idx] = 0;
-                            }
+        let event_pump = sdl_context.event_pump().unwrap();
+        //controllers plugged in already show up as ControllerDeviceAdded events, like hot-plugged ones
+        let controller_subsystem = match sdl_context.game_controller() {
+            Ok(subsystem) => Some(subsystem),
+            Err(error) => {
+                println!("No gamepad support: {}", error);
+                None
+            }
+        };
+
+        Display { canvas, event_pump, width, height, keymap, held: HashSet::new(), controller_subsystem, controllers: Vec::new(),
+            palettes: [palette].into_iter().chain(THEMES.iter().map(|(_, theme)| *theme).filter(|theme| *theme != palette)).collect(),
+            palette_index: 0 }
+    }
+
+    //feeds key presses into the keypad and returns the hotkeys pressed since the last call
+    pub fn handle_events(&mut self, chip: &mut Processor) -> Vec<Hotkey> {
+        let mut hotkeys = Vec::new();
+        for event in self.event_pump.poll_iter() {
+            match event {
+                Event::Quit {..} | Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
+                    hotkeys.push(Hotkey::Quit);
+                }
+                Event::KeyDown { keycode: Some(Keycode::F12), repeat: false, .. } => {
+                    hotkeys.push(Hotkey::DebugBreak);
+                }
+                Event::KeyDown { keycode: Some(Keycode::F11), keymod, repeat: false, .. } if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) => {
+                    hotkeys.push(Hotkey::FlickerMode);
+                }
+                Event::KeyDown { keycode: Some(Keycode::F11), repeat: false, .. } => {
+                    self.palette_index = (self.palette_index + 1) % self.palettes.len();
+                    let palette = self.palettes[self.palette_index];
+                    let name = THEMES.iter().find(|(_, theme)| *theme == palette).map_or("the ROM's own", |(name, _)| name);
+                    println!("Palette: {}", name);
+                }
+                Event::KeyDown { keycode: Some(key), keymod, repeat: false, .. } if SLOT_KEYS.contains(&key) => {
+                    let slot = SLOT_KEYS.iter().position(|x| *x == key).unwrap() as u8 + 1;
+                    if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                        hotkeys.push(Hotkey::SaveSlot(slot));
+                    } else {
+                        hotkeys.push(Hotkey::LoadSlot(slot));
+                    }
+                }
+                Event::KeyDown { keycode, scancode, .. } => update_keys(&self.keymap, &mut self.held, chip, &key_changes(keycode, scancode, true)),
+                Event::KeyUp { keycode, scancode, .. } => update_keys(&self.keymap, &mut self.held, chip, &key_changes(keycode, scancode, false)),
+                Event::ControllerDeviceAdded { which, .. } => {
+                    let Some(subsystem) = &self.controller_subsystem else { continue };
+                    match subsystem.open(which) {
+                        Ok(controller) => {
+                            println!("Gamepad connected: {}", controller.name());
+                            self.controllers.push(controller);
                         }
-                        _ => {}
+                        Err(error) => println!("Could not open gamepad {}: {}", which, error)
                     }
                 }
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    self.controllers.retain(|controller| controller.instance_id() != which);
+                    println!("Gamepad disconnected");
+                    //whatever was held on it would otherwise stay pressed
+                    let released: Vec<(Binding, bool)> = self.held.iter().filter(|binding| binding.is_gamepad()).map(|binding| (*binding, false)).collect();
+                    update_keys(&self.keymap, &mut self.held, chip, &released);
+                }
+                Event::ControllerButtonDown { button, .. } => update_keys(&self.keymap, &mut self.held, chip, &[(Binding::Button(button), true)]),
+                Event::ControllerButtonUp { button, .. } => update_keys(&self.keymap, &mut self.held, chip, &[(Binding::Button(button), false)]),
+                Event::ControllerAxisMotion { axis, value, .. } => {
+                    let deadzone = self.keymap.deadzone;
+                    update_keys(&self.keymap, &mut self.held, chip, &[(Binding::Axis(axis, true), value > deadzone), (Binding::Axis(axis, false), value < -deadzone)]);
+                }
+                _ => {}
             }
-        });
+        }
+        hotkeys
+    }
 
-}
 
-fn update(canvas: &mut Canvas<Window>, scale_factor: u32, vm_lock: &Arc<Mutex<[[u8; 64]; 32]>>) {
-    let memory: MutexGuard<[[u8; 64]; 32]> = vm_lock.lock().unwrap();
-    let pixel_on = Color::RGB(255,255,255);
-    let pixel_off = Color::RGB(0,0,0);
-    
-    let mut display_pixel_y = 0;
-    let mut display_pixel_x = 0;
-
-    for y in 0..memory.len() {
-        for x in  0..memory[0].len() {
-            let pixel = memory[y][x];
-            if pixel == 1 {
-                canvas.set_draw_color(pixel_on);
-                //println!("{} x {}", y,x);
-            } else {
-                canvas.set_draw_color(pixel_off);
-            }
+    //Backspace is held down, the main loop plays the game backwards while it is
+    pub fn rewind_held(&self) -> bool {
+        self.event_pump.keyboard_state().is_scancode_pressed(Scancode::Backspace)
+    }
 
-            canvas.fill_rect(Rect::new(display_pixel_x, display_pixel_y,  scale_factor , scale_factor)).expect("Could not draw pixel rect"); 
-            display_pixel_x += scale_factor as i32;
+    //`buzzing` while the sound timer is running. fading pixels are mixed with the background
+    pub fn update(&mut self, screen: &Phosphor, buzzing: bool) {
+        let palette = self.palettes[self.palette_index];
+        let mut colors = palette.colors;
+        if let (true, Some(buzzer)) = (buzzing, palette.buzzer) {
+            colors[0] = buzzer;
+        }
+        //each pixel covers the window pixels between its own edge and the next one's, so pixels that
+        //don't divide the window evenly are a window pixel apart in size rather than leaving gaps
+        let edge = |index: usize, count: usize, size: u32| (index as u64 * size as u64 / count as u64) as i32;
+        for y in 0..screen.height() {
+            let (top, bottom) = (edge(y, screen.height(), self.height), edge(y + 1, screen.height(), self.height));
+            for x in 0..screen.width() {
+                let (left, right) = (edge(x, screen.width(), self.width), edge(x + 1, screen.width(), self.width));
+                let (planes, brightness) = screen.get(x, y);
+                self.canvas.set_draw_color(mix(colors[0], colors[planes as usize & 0x3], brightness));
+                self.canvas.fill_rect(Rect::new(left, top, (right - left) as u32, (bottom - top) as u32)).expect("Could not draw pixel rect");
+            }
         }
-        display_pixel_x = 0;
-        display_pixel_y += scale_factor as i32;
+
+        self.canvas.present();
     }
+}
+
+//the window is `scale` window pixels per lores pixel, but never smaller than one per hires pixel
+pub fn window_size(scale: u32) -> (u32, u32) {
+    ((LORES_WIDTH as u32 * scale).max(HIRES_WIDTH as u32), (LORES_HEIGHT as u32 * scale).max(HIRES_HEIGHT as u32))
+}
 
-    canvas.present();
+//`off` at brightness 0 through `on` at 255
+fn mix(off: Color, on: Color, brightness: u8) -> Color {
+    let channel = |off: u8, on: u8| ((off as u32 * (255 - brightness as u32) + on as u32 * brightness as u32) / 255) as u8;
+    Color::RGB(channel(off.r, on.r), channel(off.g, on.g), channel(off.b, on.b))
 }
 
+//a keyboard event moves both the keycode and the scancode binding
+fn key_changes(keycode: Option<Keycode>, scancode: Option<Scancode>, down: bool) -> Vec<(Binding, bool)> {
+    keycode.map(Binding::Key).into_iter().chain(scancode.map(Binding::Scan)).map(|binding| (binding, down)).collect()
+}
+
+//applies (binding, down) changes. a CHIP-8 key stays down while any of its bindings is, so
+//releasing one of two doesn't release it
+fn update_keys(keymap: &Keymap, held: &mut HashSet<Binding>, chip: &mut Processor, changes: &[(Binding, bool)]) {
+    for (binding, down) in changes {
+        if *down {
+            held.insert(*binding);
+        } else {
+            held.remove(binding);
+        }
+    }
+    for (binding, _) in changes {
+        for key in keymap.keys_bound_to(*binding) {
+            chip.set_key(key, keymap.is_pressed(key, held));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn themes_by_name() {
+        for (name, theme) in THEMES {
+            assert_eq!(Palette::parse(name), Some(theme));
+            assert_eq!(Palette::parse(&format!(" {} ", name.to_uppercase())), Some(theme));
+        }
+        assert_eq!(Palette::default(), THEMES[0].1);
+        assert_eq!(Palette::parse("plaid"), None);
+    }
+
+    #[test]
+    fn hex_colours() {
+        let (black, white, red, green, blue) = (Color::RGB(0, 0, 0), Color::RGB(255, 255, 255),
+            Color::RGB(255, 0, 0), Color::RGB(0, 255, 0), Color::RGB(0, 0, 255));
+        //one "on" colour stands for every plane
+        assert_eq!(Palette::parse("#000000,#ffffff"), Some(Palette { colors: [black, white, white, white], buzzer: None }));
+        assert_eq!(Palette::parse("000000, FFFFFF, #ff0000"), Some(Palette { colors: [black, white, white, white], buzzer: Some(red) }));
+        assert_eq!(Palette::parse("#000000,#ff0000,#00ff00,#0000ff"), Some(Palette { colors: [black, red, green, blue], buzzer: None }));
+        assert_eq!(Palette::parse("#000000,#ff0000,#00ff00,#0000ff,#ffffff"), Some(Palette { colors: [black, red, green, blue], buzzer: Some(white) }));
+    }
+
+    #[test]
+    fn bad_specs() {
+        for spec in ["", "#000000", "#000000,#ffffff,#ff0000,#00ff00,#0000ff,#ffffff", "#000000,", "#00000,#ffffff",
+            "#0000000,#ffffff", "#00000g,#ffffff", "+00000,#ffffff", "##000000,#ffffff", "black,white"] {
+            assert_eq!(Palette::parse(spec), None, "{}", spec);
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::VecDeque;
+use chip8rs_core::{Framebuffer, Processor};
+
+//CHIP-8 games move sprites by XORing them off and drawing them again, so a frame that ends between
+//the two shows the sprite missing. these hide that at the cost of some smearing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlickerMode {
+    Off,
+    //a pixel that turns off fades out over this many frames, like a phosphor screen
+    Fade(u8),
+    //a pixel shows while it was on in any of the last this many frames
+    Blend(u8),
+    //only shows frames as they were at vblank, and holds back one that ended on a draw erasing
+    //pixels, which is usually a sprite taken off to be drawn again in the next frame
+    Vblank,
+}
+
+const DEFAULT_FADE: u8 = 4;
+const DEFAULT_BLEND: u8 = 2;
+//how many frames in a row Vblank holds back, so games that erase on purpose still move
+const VBLANK_HOLD: u8 = 2;
+
+impl FlickerMode {
+    //"off", "fade", "blend" or "vblank", fade and blend optionally followed by ":frames"
+    pub fn from_name(name: &str) -> Option<FlickerMode> {
+        let (name, frames) = match name.split_once(':') {
+            Some((name, frames)) => (name, Some(frames.parse::<u8>().ok().filter(|frames| *frames > 0)?)),
+            None => (name, None)
+        };
+        match (name.to_lowercase().as_str(), frames) {
+            ("off", None) => Some(FlickerMode::Off),
+            ("fade", frames) => Some(FlickerMode::Fade(frames.unwrap_or(DEFAULT_FADE))),
+            ("blend", frames) => Some(FlickerMode::Blend(frames.unwrap_or(DEFAULT_BLEND))),
+            ("vblank", None) => Some(FlickerMode::Vblank),
+            _ => None
+        }
+    }
+    pub fn name(&self) -> String {
+        match self {
+            FlickerMode::Off => "off".to_string(),
+            FlickerMode::Fade(frames) => format!("fade over {} frames", frames),
+            FlickerMode::Blend(frames) => format!("blend of {} frames", frames),
+            FlickerMode::Vblank => "vblank, holding back half redrawn frames".to_string(),
+        }
+    }
+}
+
+//what the window shows, built up from every emulated frame whether or not it gets presented
+pub struct Phosphor {
+    //the mode from the command line followed by the other defaults, shift+F11 steps through them
+    modes: Vec<FlickerMode>,
+    mode_index: usize,
+    width: usize,
+    height: usize,
+    //plane mask each pixel last showed and how bright it still is, 0-255
+    pixels: Vec<(u8, u8)>,
+    //Blend's last frames, newest at the back
+    history: VecDeque<Framebuffer>,
+    //frames Vblank has held back in a row
+    held: u8,
+}
+
+impl Phosphor {
+    pub fn new(mode: FlickerMode) -> Phosphor {
+        let defaults = [FlickerMode::Off, FlickerMode::Fade(DEFAULT_FADE), FlickerMode::Blend(DEFAULT_BLEND), FlickerMode::Vblank];
+        let mut modes = vec![mode];
+        modes.extend(defaults.into_iter().filter(|default| *default != mode));
+        Phosphor { modes, mode_index: 0, width: 0, height: 0, pixels: Vec::new(), history: VecDeque::new(), held: 0 }
+    }
+
+    pub fn get_mode(&self) -> FlickerMode {
+        self.modes[self.mode_index]
+    }
+
+    //switches to the next mode, starting it from a blank slate
+    pub fn next_mode(&mut self) -> FlickerMode {
+        self.mode_index = (self.mode_index + 1) % self.modes.len();
+        self.pixels.clear();
+        self.history.clear();
+        self.get_mode()
+    }
+
+    //takes the screen once per pass of the main loop. `finished` is false when the debugger stopped
+    //partway through a frame or ran none: fading and blending then don't move on, and Vblank keeps
+    //what it had
+    pub fn push(&mut self, chip: &Processor, finished: bool) {
+        let mode = self.get_mode();
+        let frame = chip.get_vmemory();
+        //a resolution switch leaves nothing to fade from
+        if (frame.width(), frame.height()) != (self.width, self.height) || self.pixels.is_empty() {
+            self.width = frame.width();
+            self.height = frame.height();
+            self.pixels = vec![(0, 0); self.width * self.height];
+            self.history.clear();
+            self.held = 0;
+        } else if mode == FlickerMode::Vblank {
+            if !finished {
+                return;
+            }
+            if chip.get_last_draw_erased() && self.held < VBLANK_HOLD {
+                self.held += 1;
+                return;
+            }
+        }
+        if finished {
+            self.held = 0;
+            if let FlickerMode::Blend(frames) = mode {
+                self.history.push_back(frame.clone());
+                while self.history.len() > frames as usize {
+                    self.history.pop_front();
+                }
+            }
+        }
+        for (index, pixel) in frame.pixels().iter().enumerate() {
+            let shown = &mut self.pixels[index];
+            *shown = match mode {
+                FlickerMode::Off | FlickerMode::Vblank => (*pixel, 255),
+                FlickerMode::Blend(_) => (self.history.iter().fold(*pixel, |mask, frame| mask | frame.pixels()[index]), 255),
+                FlickerMode::Fade(_) if *pixel != 0 => (*pixel, 255),
+                FlickerMode::Fade(_) if !finished => *shown,
+                FlickerMode::Fade(frames) => (shown.0, shown.1.saturating_sub(255u8.div_ceil(frames))),
+            };
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    //(plane mask, brightness) of the pixel at x, y
+    pub fn get(&self, x: usize, y: usize) -> (u8, u8) {
+        self.pixels[y * self.width + x]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chip8rs_core::{Platform, FONTSET};
+    use super::*;
+
+    #[test]
+    fn mode_names() {
+        assert_eq!(FlickerMode::from_name("off"), Some(FlickerMode::Off));
+        assert_eq!(FlickerMode::from_name("Fade"), Some(FlickerMode::Fade(DEFAULT_FADE)));
+        assert_eq!(FlickerMode::from_name("blend:3"), Some(FlickerMode::Blend(3)));
+        assert_eq!(FlickerMode::from_name("vblank"), Some(FlickerMode::Vblank));
+        for name in ["fade:0", "blend:x", "off:2", "vblank:2", "smear"] {
+            assert_eq!(FlickerMode::from_name(name), None, "{}", name);
+        }
+    }
+
+    //the 0 glyph in the top left corner: LD F, V0, then DRW V0, V0, 5 draws it, erases it and draws it again
+    fn glyph() -> Processor {
+        let mut chip = Processor::new(FONTSET, Platform::Chip8.default_quirks(), Platform::Chip8);
+        chip.load_rom(&[0xF0, 0x29, 0xD0, 0x05, 0xD0, 0x05, 0xD0, 0x05]).unwrap();
+        chip.cycle().unwrap();
+        chip
+    }
+
+    #[test]
+    fn vblank_holds_frames_that_end_erasing() {
+        let mut chip = glyph();
+        let mut vblank = Phosphor::new(FlickerMode::Vblank);
+        chip.cycle().unwrap();
+        vblank.push(&chip, true);
+        assert_eq!(vblank.get(0, 0), (1, 255));
+        chip.cycle().unwrap();
+        for _ in 0..VBLANK_HOLD {
+            vblank.push(&chip, true);
+            assert_eq!(vblank.get(0, 0), (1, 255), "the erased glyph is held back");
+        }
+        //a game that stays erased shows up after a few frames
+        vblank.push(&chip, true);
+        assert_eq!(vblank.get(0, 0), (0, 255));
+        chip.cycle().unwrap();
+        vblank.push(&chip, true);
+        assert_eq!(vblank.get(0, 0), (1, 255));
+    }
+
+    #[test]
+    fn unfinished_frames_dont_fade_or_blend() {
+        let mut chip = glyph();
+        chip.cycle().unwrap();
+        let mut fade = Phosphor::new(FlickerMode::Fade(4));
+        let mut blend = Phosphor::new(FlickerMode::Blend(2));
+        fade.push(&chip, true);
+        blend.push(&chip, true);
+        chip.cycle().unwrap();
+        for _ in 0..8 {
+            fade.push(&chip, false);
+            blend.push(&chip, false);
+        }
+        assert_eq!((fade.get(0, 0), blend.get(0, 0)), ((1, 255), (1, 255)));
+        fade.push(&chip, true);
+        blend.push(&chip, true);
+        assert_eq!((fade.get(0, 0), blend.get(0, 0)), ((1, 191), (1, 255)));
+        //the blended history only ever took the two finished frames
+        blend.push(&chip, true);
+        assert_eq!(blend.get(0, 0), (0, 255));
+    }
+
+    #[test]
+    fn vblank_waits_for_the_frame_to_finish() {
+        let mut chip = Processor::new(FONTSET, Platform::Chip8.default_quirks(), Platform::Chip8);
+        //LD F, V0 then DRW V0, V0, 5: the 0 glyph in the top left corner
+        chip.load_rom(&[0xF0, 0x29, 0xD0, 0x05]).unwrap();
+        let mut off = Phosphor::new(FlickerMode::Off);
+        let mut vblank = Phosphor::new(FlickerMode::Vblank);
+        vblank.push(&chip, true);
+        chip.cycle().unwrap();
+        chip.cycle().unwrap();
+        //stopped by the debugger after the draw
+        off.push(&chip, false);
+        vblank.push(&chip, false);
+        assert_eq!((off.get(0, 0), vblank.get(0, 0)), ((1, 255), (0, 255)));
+        vblank.push(&chip, true);
+        assert_eq!(vblank.get(0, 0), (1, 255));
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::HashSet;
+use sdl2::controller::{Axis, Button};
+use sdl2::keyboard::{Keycode, Scancode};
+
+//a keyboard key or gamepad input bound to a CHIP-8 key. keycodes follow the layout ("Q" is wherever
+//the layout puts Q), scancodes are physical positions ("scancode:Q" is the key left of W on any layout)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(Keycode),
+    Scan(Scancode),
+    Button(Button),
+    //a stick or trigger pushed past the deadzone, true for the positive direction (right or down)
+    Axis(Axis, bool),
+}
+
+impl Binding {
+    //SDL key names like "Q", "Up" or "Keypad 5", or "scancode:" followed by an SDL scancode name
+    pub fn parse_key(name: &str) -> Option<Binding> {
+        match name.strip_prefix("scancode:") {
+            Some(scancode) => Scancode::from_name(scancode).map(Binding::Scan),
+            None => Keycode::from_name(name).map(Binding::Key),
+        }
+    }
+
+    //SDL GameController names: buttons like "a" or "dpleft", axes with a direction like "leftx-"
+    pub fn parse_gamepad(name: &str) -> Option<Binding> {
+        if let Some(axis) = name.strip_suffix('+') {
+            return Axis::from_string(axis).map(|axis| Binding::Axis(axis, true));
+        }
+        if let Some(axis) = name.strip_suffix('-') {
+            return Axis::from_string(axis).map(|axis| Binding::Axis(axis, false));
+        }
+        Button::from_string(name).map(Binding::Button)
+    }
+
+    pub fn is_gamepad(&self) -> bool {
+        matches!(self, Binding::Button(_) | Binding::Axis(..))
+    }
+}
+
+//the controls the ROM database can give a CHIP-8 key for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Control {
+    Up,
+    Down,
+    Left,
+    Right,
+    A,
+    B,
+}
+
+impl Control {
+    pub fn from_name(name: &str) -> Option<Control> {
+        match name.to_lowercase().as_str() {
+            "up" => Some(Control::Up),
+            "down" => Some(Control::Down),
+            "left" => Some(Control::Left),
+            "right" => Some(Control::Right),
+            "a" => Some(Control::A),
+            "b" => Some(Control::B),
+            _ => None
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            Control::Up => "up",
+            Control::Down => "down",
+            Control::Left => "left",
+            Control::Right => "right",
+            Control::A => "a",
+            Control::B => "b",
+        }
+    }
+
+    //arrow keys, D-pad and left stick for the directions; gamepad A/B and Space/Left Shift for the buttons
+    fn bindings(&self) -> [Binding; 3] {
+        match self {
+            Control::Up => [Binding::Key(Keycode::Up), Binding::Button(Button::DPadUp), Binding::Axis(Axis::LeftY, false)],
+            Control::Down => [Binding::Key(Keycode::Down), Binding::Button(Button::DPadDown), Binding::Axis(Axis::LeftY, true)],
+            Control::Left => [Binding::Key(Keycode::Left), Binding::Button(Button::DPadLeft), Binding::Axis(Axis::LeftX, false)],
+            Control::Right => [Binding::Key(Keycode::Right), Binding::Button(Button::DPadRight), Binding::Axis(Axis::LeftX, true)],
+            Control::A => [Binding::Key(Keycode::Space), Binding::Button(Button::A), Binding::Axis(Axis::TriggerRight, true)],
+            Control::B => [Binding::Key(Keycode::LShift), Binding::Button(Button::B), Binding::Axis(Axis::TriggerLeft, true)],
+        }
+    }
+}
+
+//which keyboard keys and gamepad inputs press each of the 16 CHIP-8 keys
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keymap {
+    bindings: [Vec<Binding>; 16],
+    //how far a stick has to move before it counts, out of 32767
+    pub deadzone: i16,
+}
+
+impl Keymap {
+    //replaces the keyboard or the gamepad bindings of `key`, leaving the other kind as it was
+    pub fn set_bindings(&mut self, key: usize, gamepad: bool, bindings: Vec<Binding>) {
+        self.bindings[key].retain(|binding| binding.is_gamepad() != gamepad);
+        self.bindings[key].extend(bindings);
+    }
+
+    //moves each control's bindings onto the CHIP-8 key the ROM uses for it. the 1234/QWER/ASDF/ZXCV
+    //keys stay where they are
+    pub fn apply_controls(&mut self, controls: &[(Control, usize)]) {
+        for (control, key) in controls {
+            let bindings = control.bindings();
+            for bound in self.bindings.iter_mut() {
+                bound.retain(|binding| !bindings.contains(binding));
+            }
+            self.bindings[*key].extend(bindings);
+        }
+    }
+
+    //CHIP-8 keys that have `binding` among their bindings
+    pub fn keys_bound_to(&self, binding: Binding) -> impl Iterator<Item = usize> + '_ {
+        (0..16).filter(move |key| self.bindings[*key].contains(&binding))
+    }
+
+    //whether `key` is held given the keyboard keys that are down
+    pub fn is_pressed(&self, key: usize, held: &HashSet<Binding>) -> bool {
+        self.bindings[key].iter().any(|binding| held.contains(binding))
+    }
+}
+
+impl Default for Keymap {
+    //keys 0-F on the QWERTY rows 1234, QWER, ASDF, ZXCV. on a gamepad the D-pad and left stick
+    //press 2/4/6/8, the directions most games use, and A presses 5
+    fn default() -> Keymap {
+        const LAYOUT: [Keycode; 16] = [
+            Keycode::Num1, Keycode::Num2, Keycode::Num3, Keycode::Num4,
+            Keycode::Q, Keycode::W, Keycode::E, Keycode::R,
+            Keycode::A, Keycode::S, Keycode::D, Keycode::F,
+            Keycode::Z, Keycode::X, Keycode::C, Keycode::V
+        ];
+        let mut keymap = Keymap { bindings: LAYOUT.map(|keycode| vec![Binding::Key(keycode)]), deadzone: 8000 };
+        let gamepad = [
+            (0x2, Button::DPadUp, Axis::LeftY, false),
+            (0x8, Button::DPadDown, Axis::LeftY, true),
+            (0x4, Button::DPadLeft, Axis::LeftX, false),
+            (0x6, Button::DPadRight, Axis::LeftX, true),
+        ];
+        for (key, button, axis, positive) in gamepad {
+            keymap.set_bindings(key, true, vec![Binding::Button(button), Binding::Axis(axis, positive)]);
+        }
+        keymap.set_bindings(0x5, true, vec![Binding::Button(Button::A)]);
+        keymap
+    }
+}
+
+//"0"-"9" and "A"-"F"
+pub fn parse_chip8_key(name: &str) -> Option<usize> {
+    match name.len() {
+        1 => usize::from_str_radix(name, 16).ok(),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_names() {
+        assert_eq!(Binding::parse_key("Q"), Some(Binding::Key(Keycode::Q)));
+        assert_eq!(Binding::parse_key("Keypad 5"), Some(Binding::Key(Keycode::Kp5)));
+        assert_eq!(Binding::parse_key("scancode:Z"), Some(Binding::Scan(Scancode::Z)));
+        assert_eq!(Binding::parse_key("NoSuchKey"), None);
+        assert_eq!(Binding::parse_key("scancode:NoSuchKey"), None);
+        assert_eq!((parse_chip8_key("0"), parse_chip8_key("f"), parse_chip8_key("G"), parse_chip8_key("10")), (Some(0), Some(15), None, None));
+    }
+
+    #[test]
+    fn gamepad_names() {
+        assert_eq!(Binding::parse_gamepad("a"), Some(Binding::Button(Button::A)));
+        assert_eq!(Binding::parse_gamepad("dpleft"), Some(Binding::Button(Button::DPadLeft)));
+        assert_eq!(Binding::parse_gamepad("leftx-"), Some(Binding::Axis(Axis::LeftX, false)));
+        assert_eq!(Binding::parse_gamepad("lefty+"), Some(Binding::Axis(Axis::LeftY, true)));
+        assert_eq!(Binding::parse_gamepad("righttrigger+"), Some(Binding::Axis(Axis::TriggerRight, true)));
+        //an axis needs a direction and a button can't have one
+        for name in ["leftx", "a+", "nosuchbutton", "Q"] {
+            assert_eq!(Binding::parse_gamepad(name), None, "{}", name);
+        }
+        assert!(Binding::Axis(Axis::LeftX, true).is_gamepad() && !Binding::Key(Keycode::Q).is_gamepad());
+    }
+
+    #[test]
+    fn keys_take_several_bindings() {
+        let mut keymap = Keymap::default();
+        keymap.set_bindings(0x5, false, vec![Binding::Key(Keycode::Up), Binding::Scan(Scancode::K)]);
+        assert_eq!(keymap.bindings[0x5], [Binding::Button(Button::A), Binding::Key(Keycode::Up), Binding::Scan(Scancode::K)]);
+        for binding in [Binding::Key(Keycode::Up), Binding::Scan(Scancode::K)] {
+            assert!(keymap.is_pressed(0x5, &HashSet::from([binding])));
+        }
+        //W was 5's only keyboard binding
+        assert!(!keymap.is_pressed(0x5, &HashSet::from([Binding::Key(Keycode::W)])));
+        //a binding can press more than one key
+        keymap.set_bindings(0x6, false, vec![Binding::Key(Keycode::Up)]);
+        assert_eq!(keymap.keys_bound_to(Binding::Key(Keycode::Up)).collect::<Vec<_>>(), [0x5, 0x6]);
+    }
+
+    #[test]
+    fn controls_move_to_the_roms_keys() {
+        let mut keymap = Keymap::default();
+        keymap.apply_controls(&[(Control::Left, 0x7), (Control::A, 0x6)]);
+        //the D-pad left used to press 4
+        assert_eq!(keymap.keys_bound_to(Binding::Button(Button::DPadLeft)).collect::<Vec<_>>(), [0x7]);
+        assert_eq!(keymap.keys_bound_to(Binding::Button(Button::A)).collect::<Vec<_>>(), [0x6]);
+        assert_eq!(keymap.keys_bound_to(Binding::Key(Keycode::Left)).collect::<Vec<_>>(), [0x7]);
+        assert_eq!(keymap.keys_bound_to(Binding::Key(Keycode::Q)).collect::<Vec<_>>(), [0x4]);
+    }
+}
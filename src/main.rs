@@ -1,62 +1,408 @@
-use std::{fs, thread, time::Duration, sync::{Mutex, Arc}, path::PathBuf};
+use std::{env, fs, process, thread, time::{Duration, Instant, SystemTime, UNIX_EPOCH}, fs::File, io::BufWriter, path::PathBuf};
 use native_dialog::{FileDialog, MessageType, MessageDialog};
-use sdl2::keyboard::Keycode;
-use winconsole::console;
-use crate::{display::start_display, chip8::Processor};
+use chip8rs_core::{assemble, disasm, sha1_hex, Movie, Platform, Processor, RewindBuffer, Rng, FONTSET};
+use chip8rs_core::headless::{self, Hooks, Limit};
+use chip8rs_core::trace::{self, TraceWriter};
+use crate::{audio::Audio, config::Config, debugger::DebugConsole, display::{Display, Hotkey}, flicker::{FlickerMode, Phosphor}, movie::MovieSession};
 
-mod chip8;
+mod audio;
+mod cli;
+mod config;
+mod debugger;
 mod display;
-const FONTSET: [u8; 80] = [
-    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-    0x20, 0x60, 0x20, 0x20, 0x70, // 1
-    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-    0xF0, 0x80, 0xF0, 0x80, 0x80  // F
-];
-const KEY_MAPPING: [Keycode; 16] = [
-    Keycode::Num1, Keycode::Num2, Keycode::Num3, Keycode::Num4,
-    Keycode::Q, Keycode::W, Keycode::E, Keycode::R,
-    Keycode::A, Keycode::S, Keycode::D, Keycode::F,
-    Keycode::Z, Keycode::X, Keycode::C, Keycode::V
-];
+mod flicker;
+mod keymap;
+mod movie;
+mod romdb;
+mod savestate;
 
 fn main() {
-    let (clock_speed, scale_factor, refresh_rate) = (1000, 10, 30);
-    let (clock_delay, refresh_delay) = (1.0 / clock_speed as f32, 1.0 / refresh_rate as f32); //ms
+    match env::args().nth(1).as_deref() {
+        Some("disasm") => return disassemble(env::args().skip(2)),
+        Some("asm") => return assemble_source(env::args().skip(2)),
+        Some("trace") => return print_trace(env::args().skip(2)),
+        _ => {}
+    }
+    let options = match cli::parse_args(env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", cli::USAGE);
+            return;
+        }
+        Err(error) => {
+            eprintln!("error: {}\n\n{}", error, cli::USAGE);
+            process::exit(2);
+        }
+    };
+    //timers run at 60hz so one emulated frame = one timer tick
+    let frame_rate = 60;
+    let frame_delay = Duration::from_secs_f32(1.0 / frame_rate as f32);
     
-    let filename: String = get_romfile().unwrap();
+    let filename: String = match &options.rom {
+        Some(path) => path.to_string_lossy().to_string(),
+        None if options.headless => {
+            eprintln!("error: --headless needs a ROM path");
+            process::exit(2);
+        }
+        None => match get_romfile() {
+            Some(filename) => filename,
+            None => return
+        }
+    };
     
-    let rom_data: Vec<u8> = fs::read(&filename)
-        .expect("Could not read from selected filename");
-    let mut chip: Processor = chip8::Processor::new(FONTSET);
-    chip.load_rom(&rom_data);
+    let rom_data: Vec<u8> = match fs::read(&filename) {
+        Ok(data) => data,
+        Err(error) => {
+            eprintln!("error: could not read {}: {}", filename, error);
+            process::exit(1);
+        }
+    };
+    let rom_hash = sha1_hex(&rom_data);
+    //the command line wins over the ROM database, which wins over the defaults
+    let rom_info = match romdb::lookup(&rom_hash) {
+        Ok(info) => info.unwrap_or_default(),
+        Err(error) => {
+            eprintln!("error: {}", error);
+            process::exit(1);
+        }
+    };
+    let platform = options.platform.or(rom_info.platform).unwrap_or(Platform::XoChip);
+    let quirks = options.quirks.or(rom_info.quirks.as_ref().map(|quirks| quirks.for_platform(platform))).unwrap_or(platform.default_quirks());
+    let mut instructions_per_frame = match (options.instructions_per_second, rom_info.tickrate) {
+        (Some(instructions_per_second), _) => (instructions_per_second / frame_rate).max(1),
+        (None, Some(tickrate)) => tickrate,
+        (None, None) => 1000 / frame_rate
+    };
+    let mut chip: Processor = Processor::new(FONTSET, quirks, platform);
+    if let Err(error) = chip.load_rom(&rom_data) {
+        eprintln!("error: {}: {}", filename, error);
+        process::exit(1);
+    }
+    println!("Loaded rom! {} bytes.", rom_data.len());
+    if let Some(title) = &rom_info.title {
+        println!("{}{}", title, rom_info.author.as_ref().map_or(String::new(), |author| format!(" by {}", author)));
+    }
+    if !rom_info.keys.is_empty() {
+        println!("Controls: {}", romdb::describe_keys(&rom_info.keys));
+    }
+    //printed so a run can be repeated with --seed
+    let seed = options.seed.unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64));
+    chip.set_rng(Rng::new(options.rng, seed));
+    println!("Random seed: {}", seed);
+    if let Some(path) = &options.load_state {
+        if let Err(error) = savestate::load_file(&mut chip, path) {
+            eprintln!("error: {}", error);
+            process::exit(1);
+        }
+    }
+    let mut movie = match (&options.record, &options.play) {
+        (Some(path), _) => Some(MovieSession::Record(Movie::new(&chip, &rom_hash, seed, instructions_per_frame), path.clone())),
+        (_, Some(path)) => match MovieSession::play(&mut chip, &rom_hash, path) {
+            Ok(session) => Some(session),
+            Err(error) => {
+                eprintln!("error: {}", error);
+                process::exit(1);
+            }
+        },
+        _ => None
+    };
+    //replays run at the speed they were recorded at
+    if let Some(recorded) = movie.as_ref().and_then(MovieSession::get_instructions_per_frame) {
+        instructions_per_frame = recorded;
+    }
+
+    let mut tracer = options.trace.as_ref().map(|path| {
+        let file = File::create(path).and_then(|file| TraceWriter::new(BufWriter::new(file), options.trace_format, options.trace_filter));
+        match file {
+            Ok(tracer) => tracer,
+            Err(error) => {
+                eprintln!("error: could not create {}: {}", path.display(), error);
+                process::exit(1);
+            }
+        }
+    });
+    chip.set_tracing(tracer.is_some());
+
+    if options.headless {
+        run_headless(&mut chip, instructions_per_frame, options.frames, &mut tracer, &mut movie);
+        return;
+    }
     
-    //get locks from chip8 and pass to display and 60hz timer
-    let vm_lock: Arc<Mutex<[[u8; 64]; 32]>> = chip.get_vmemory();
-    let kp_lock: Arc<Mutex<[u8; 16]>> = chip.get_keypad();
-    let timer_locks: (Arc<Mutex<u8>>,Arc<Mutex<u8>>) = chip.get_timers();
-    start_display(scale_factor, refresh_delay, KEY_MAPPING, vm_lock, kp_lock);
-    start_timers(timer_locks);
+    let config = match Config::load(options.config.as_deref(), &rom_hash, &rom_info) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            process::exit(1);
+        }
+    };
+    let sdl_context = sdl2::init().unwrap();
+    let palette = options.palette.or(config.palette).unwrap_or_default();
+    let mut display = Display::new(&sdl_context, options.scale, config.keymap, palette);
+    let mut phosphor = Phosphor::new(options.flicker);
+    let mut audio = match Audio::new(&sdl_context, options.audio) {
+        Ok(audio) => Some(audio),
+        Err(error) => {
+            println!("No audio device, running without sound: {}", error);
+            None
+        }
+    };
     
     println!("Rom file path: {}", &filename);
-    println!("Scale factor: {}x so resolution of {}x{}", &scale_factor, 64*scale_factor, 32*scale_factor);
-    println!("Refresh rate: {}hz so delay time of {} seconds", &refresh_rate, &refresh_delay);
-    println!("Processor running at {}hz so delay time of {} seconds", clock_speed, &clock_delay);
+    let (width, height) = display::window_size(options.scale);
+    println!("Scale factor: {}x so resolution of {}x{}", options.scale, width, height);
+    println!("Running at {} frames per second, {} instructions per frame ({}hz)", frame_rate, instructions_per_frame, instructions_per_frame * frame_rate);
+    if options.flicker != FlickerMode::Off {
+        println!("Flicker reduction: {}", options.flicker.name());
+    }
+
+    let mut debug_console = options.debug.then(|| DebugConsole::new(&chip));
+    let rewind_seconds = if movie.is_some() { 0 } else { options.rewind_seconds };
+    let mut rewind = RewindBuffer::new(rewind_seconds, options.rewind_interval);
+    let mut frame_count: u64 = 0;
+    //presents fps out of every 60 frames
+    let mut present_credit = 0;
+    while !chip.has_exited() && options.frames.is_none_or(|frames| frame_count < frames) {
+        let frame_start = Instant::now();
+        let hotkeys = display.handle_events(&mut chip);
+        if hotkeys.contains(&Hotkey::Quit) {
+            break;
+        }
+        for hotkey in hotkeys {
+            match hotkey {
+                Hotkey::SaveSlot(slot) => match savestate::save_slot(&chip, &rom_hash, slot) {
+                    Ok(path) => println!("Saved slot {} to {}", slot, path.display()),
+                    Err(error) => println!("Could not save slot {}: {}", slot, error)
+                },
+                Hotkey::LoadSlot(slot) if movie.is_some() => println!("Can't load slot {} while a movie is recording or playing", slot),
+                Hotkey::LoadSlot(slot) => match savestate::load_slot(&mut chip, &rom_hash, slot) {
+                    Ok(path) => {
+                        //history from before the load would rewind into a different timeline
+                        rewind.clear();
+                        println!("Loaded slot {} from {}", slot, path.display());
+                    }
+                    Err(error) => println!("Could not load slot {}: {}", slot, error)
+                },
+                Hotkey::DebugBreak => {
+                    if let Some(console) = debug_console.as_mut() {
+                        console.pause(&chip);
+                    }
+                }
+                Hotkey::FlickerMode => {
+                    let mode = phosphor.next_mode();
+                    println!("Flicker reduction: {}", mode.name());
+                }
+                Hotkey::Quit => {}
+            }
+        }
+        if let Some(console) = debug_console.as_mut() {
+            console.poll(&mut chip);
+        }
+        //rewinding goes back to states recorded between frames
+        let frame_finished = if display.rewind_held() {
+            if let Err(error) = rewind.rewind(&mut chip) {
+                println!("Could not rewind, history dropped: {}", error);
+                rewind.clear();
+            }
+            true
+        } else if let Some(console) = debug_console.as_mut() {
+            let finished = console.run_frame(&mut chip, instructions_per_frame);
+            if finished {
+                rewind.record(&chip);
+                frame_count += 1;
+            }
+            finished
+        } else {
+            if let Some(movie) = movie.as_mut() {
+                movie.before_frame(&mut chip, frame_count);
+            }
+            if let Err(error) = chip.run_frame(instructions_per_frame) {
+                MessageDialog::new()
+                    .set_type(MessageType::Error)
+                    .set_title("Emulator crashed")
+                    .set_text(&error.to_string())
+                    .show_alert()
+                    .unwrap();
+                break;
+            }
+            rewind.record(&chip);
+            frame_count += 1;
+            if let Some(movie) = movie.as_ref() {
+                movie.after_frame(&chip, frame_count);
+            }
+            true
+        };
+        write_trace(&mut chip, &mut tracer);
+        if let Some(audio) = audio.as_mut() {
+            audio.update(&chip);
+        }
+        //fed every frame, so fading and blending carry on through frames that aren't presented
+        phosphor.push(&chip, frame_finished);
+        present_credit += options.fps;
+        if present_credit >= frame_rate {
+            present_credit -= frame_rate;
+            display.update(&phosphor, chip.get_timers().1 > 0);
+        }
+
+        if let Some(remaining) = frame_delay.checked_sub(frame_start.elapsed()) {
+            thread::sleep(remaining);
+        }
+    }
+    write_trace(&mut chip, &mut tracer);
+    if let Some(tracer) = tracer.as_mut() {
+        if let Err(error) = tracer.flush() {
+            eprintln!("error: could not write trace: {}", error);
+        }
+    }
+    if let Some(movie) = movie.as_mut() {
+        movie.finish(&chip);
+    }
+}
 
-    loop {
-        thread::sleep(Duration::from_secs_f32(clock_delay));
-        chip.cycle();
+type Tracer = Option<TraceWriter<BufWriter<File>>>;
+
+//writes out what the processor traced since the last call. a failed write stops tracing rather than the emulator
+fn write_trace(chip: &mut Processor, tracer: &mut Tracer) {
+    let Some(writer) = tracer.as_mut() else { return };
+    if let Err(error) = writer.write(&chip.take_trace()) {
+        eprintln!("error: could not write trace, tracing stopped: {}", error);
+        chip.set_tracing(false);
+        *tracer = None;
+    }
+}
+
+//what --headless does around each frame of headless::run
+struct HeadlessHooks<'a> {
+    tracer: &'a mut Tracer,
+    movie: &'a mut Option<MovieSession>,
+    //without --frames a played movie ends the run
+    stop_with_movie: bool,
+    desynced: bool,
+}
+
+impl Hooks for HeadlessHooks<'_> {
+    fn before_frame(&mut self, chip: &mut Processor, frame: u64) -> bool {
+        if self.stop_with_movie && self.movie.as_ref().is_some_and(|movie| movie.is_over(frame)) {
+            return false;
+        }
+        if let Some(movie) = self.movie.as_mut() {
+            movie.before_frame(chip, frame);
+        }
+        true
+    }
+    fn after_frame(&mut self, chip: &mut Processor, frames: u64) {
+        write_trace(chip, self.tracer);
+        if let Some(movie) = self.movie.as_ref() {
+            self.desynced |= !movie.after_frame(chip, frames);
+        }
+    }
+}
+
+//no window, no sound, no frame pacing: runs until the ROM exits, faults, the frame limit is hit or
+//the movie being played runs out
+fn run_headless(chip: &mut Processor, instructions_per_frame: u32, frames: Option<u64>, tracer: &mut Tracer, movie: &mut Option<MovieSession>) {
+    let mut hooks = HeadlessHooks { tracer, movie, stop_with_movie: frames.is_none(), desynced: false };
+    let result = headless::run(chip, instructions_per_frame, Limit::Frames(frames.unwrap_or(u64::MAX)), &mut hooks);
+    let desynced = hooks.desynced;
+    //a fault leaves the trace of its frame unwritten
+    write_trace(chip, tracer);
+    if let Err(error) = &result {
+        eprintln!("Emulator crashed after {} frames: {}", chip.get_frame_count(), error);
+    }
+    //process::exit skips destructors, so the trace and movie have to be written first
+    if let Some(tracer) = tracer.as_mut() {
+        if let Err(error) = tracer.flush() {
+            eprintln!("error: could not write trace: {}", error);
+        }
+    }
+    if let Some(movie) = movie.as_mut() {
+        movie.finish(chip);
+    }
+    match result {
+        Ok(frames) if !desynced => println!("Ran {} frames, {} instructions", frames, chip.get_cycle_count()),
+        _ => process::exit(1)
+    }
+}
+
+fn disassemble<I: Iterator<Item = String>>(args: I) {
+    let options = match cli::parse_disasm_args(args) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", cli::DISASM_USAGE);
+            return;
+        }
+        Err(error) => {
+            eprintln!("error: {}\n\n{}", error, cli::DISASM_USAGE);
+            process::exit(2);
+        }
+    };
+    let rom_data = match fs::read(&options.rom) {
+        Ok(data) => data,
+        Err(error) => {
+            eprintln!("error: could not read {}: {}", options.rom.display(), error);
+            process::exit(1);
+        }
+    };
+    print!("{}", disasm::disassemble(&rom_data, options.origin, options.platform, options.syntax));
+}
+
+fn assemble_source<I: Iterator<Item = String>>(args: I) {
+    let options = match cli::parse_asm_args(args) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", cli::ASM_USAGE);
+            return;
+        }
+        Err(error) => {
+            eprintln!("error: {}\n\n{}", error, cli::ASM_USAGE);
+            process::exit(2);
+        }
+    };
+    let source = match fs::read_to_string(&options.source) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("error: could not read {}: {}", options.source.display(), error);
+            process::exit(1);
+        }
+    };
+    let rom = match assemble(&source) {
+        Ok(rom) => rom,
+        Err(error) => {
+            eprintln!("{}: {}", options.source.display(), error);
+            process::exit(1);
+        }
+    };
+    if let Err(error) = fs::write(&options.output, &rom) {
+        eprintln!("error: could not write {}: {}", options.output.display(), error);
+        process::exit(1);
+    }
+    println!("Wrote {} bytes to {}", rom.len(), options.output.display());
+}
+
+fn print_trace<I: Iterator<Item = String>>(args: I) {
+    let path = match cli::parse_trace_args(args) {
+        Ok(Some(path)) => path,
+        Ok(None) => {
+            println!("{}", cli::TRACE_USAGE);
+            return;
+        }
+        Err(error) => {
+            eprintln!("error: {}\n\n{}", error, cli::TRACE_USAGE);
+            process::exit(2);
+        }
+    };
+    let data = match fs::read(&path) {
+        Ok(data) => data,
+        Err(error) => {
+            eprintln!("error: could not read {}: {}", path.display(), error);
+            process::exit(1);
+        }
+    };
+    let Some(entries) = trace::read_binary(&data) else {
+        eprintln!("error: {} is not a binary trace or is truncated", path.display());
+        process::exit(1);
+    };
+    for entry in entries {
+        println!("{}", entry.to_line());
     }
 }
 
@@ -66,10 +412,7 @@ fn get_romfile() -> Option<String> {
         .show_open_single_file()
         .unwrap();
 
-    let path: PathBuf = match path {
-        Some(path) => path,
-        None => return None,
-    };
+    let path: PathBuf = path?;
 
     let yes: bool = MessageDialog::new()
         .set_type(MessageType::Info)
@@ -78,29 +421,8 @@ fn get_romfile() -> Option<String> {
         .show_confirm()
         .unwrap();
     if yes {
-        return Some(path.to_str().unwrap().to_string());
+        Some(path.to_str().unwrap().to_string())
     } else {
-        return get_romfile();
+        get_romfile()
     }
 }
-
-fn start_timers(timers: (Arc<Mutex<u8>>,Arc<Mutex<u8>>)) {
-    let delay_lock: Arc<Mutex<u8>> = timers.0;
-    let sound_lock: Arc<Mutex<u8>> = timers.1;
-    let delay: f32 = 1.0 / 60.0;
-
-    thread::spawn(move || {
-        loop {
-            thread::sleep(Duration::from_secs_f32(delay));
-            let (mut delay_timer, mut sound_timer) = (delay_lock.lock().unwrap(), sound_lock.lock().unwrap());
-            if *delay_timer > 0{
-                *delay_timer -= 1
-            }
-            if *sound_timer > 0 {
-                console::beep(800, 50);
-                *sound_timer -= 1
-            }
-    
-        }
-    });
-}
\ No newline at end of file
@@ -0,0 +1,73 @@
+use std::{fs, path::PathBuf};
+use chip8rs_core::{Movie, Processor};
+
+//--record or --play. Both need every frame to run in order from the start state, so rewind and
+//loading save slots are off while one is active
+pub enum MovieSession {
+    Record(Movie, PathBuf),
+    Play(Movie),
+}
+
+impl MovieSession {
+    //loads a movie and puts the processor at its start
+    pub fn play(chip: &mut Processor, rom_hash: &str, path: &PathBuf) -> Result<MovieSession, String> {
+        let data = fs::read(path).map_err(|error| format!("could not read {}: {}", path.display(), error))?;
+        let movie = Movie::from_bytes(&data).map_err(|error| format!("{}: {}", path.display(), error))?;
+        movie.start(chip, rom_hash).map_err(|error| format!("{}: {}", path.display(), error))?;
+        println!("Playing {} frames from {} (recorded with seed {})", movie.len(), path.display(), movie.get_seed());
+        Ok(MovieSession::Play(movie))
+    }
+
+    //instructions per frame the movie was recorded at, None when recording
+    pub fn get_instructions_per_frame(&self) -> Option<u32> {
+        match self {
+            MovieSession::Play(movie) => Some(movie.get_instructions_per_frame()),
+            MovieSession::Record(..) => None,
+        }
+    }
+
+    //true once a played movie has no frames left
+    pub fn is_over(&self, frame: u64) -> bool {
+        matches!(self, MovieSession::Play(movie) if frame >= movie.len() as u64)
+    }
+
+    //call just before frame `frame` runs. past the end of a movie the keys are released and input
+    //is left to the player
+    pub fn before_frame(&mut self, chip: &mut Processor, frame: u64) {
+        match self {
+            MovieSession::Record(movie, _) => movie.record(chip.get_keypad()),
+            MovieSession::Play(movie) => {
+                if !movie.apply(chip, frame) && frame == movie.len() as u64 {
+                    for key in 0..16 {
+                        chip.set_key(key, false);
+                    }
+                }
+            }
+        }
+    }
+
+    //call after a frame has run, `frames` counting it. reports whether a played movie ended where
+    //it was recorded; false only on a desync
+    pub fn after_frame(&self, chip: &Processor, frames: u64) -> bool {
+        let MovieSession::Play(movie) = self else { return true };
+        if frames != movie.len() as u64 {
+            return true;
+        }
+        match movie.verify(chip) {
+            Some(true) => println!("Movie finished after {} frames and matches the recording", frames),
+            Some(false) => println!("Movie finished after {} frames but desynced, the final state differs from the recording", frames),
+            None => println!("Movie finished after {} frames (it has no final state to check against)", frames),
+        }
+        movie.verify(chip) != Some(false)
+    }
+
+    //writes out a recording
+    pub fn finish(&mut self, chip: &Processor) {
+        let MovieSession::Record(movie, path) = self else { return };
+        movie.finish(chip);
+        match fs::write(&*path, movie.to_bytes()) {
+            Ok(()) => println!("Recorded {} frames to {}", movie.len(), path.display()),
+            Err(error) => eprintln!("error: could not write {}: {}", path.display(), error),
+        }
+    }
+}
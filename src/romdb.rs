@@ -0,0 +1,222 @@
+use std::fs;
+use toml::{Table, Value};
+use chip8rs_core::{Platform, Quirks};
+use crate::config;
+use crate::display::Palette;
+use crate::keymap::{parse_chip8_key, Control};
+
+//ROMs known by SHA-1, in the spirit of the community chip-8-database. data/roms.toml ships with
+//chiprs and chip8rs/roms.toml in the config directory adds entries or overrides single fields:
+//
+//  [0123...cdef]
+//  title = "Brix"
+//  author = "Andreas Gustafsson"
+//  platform = "chip8"
+//  quirks = "vip"                          # a preset, or a table of single quirks
+//  tickrate = 15                           # instructions per frame
+//  colors = ["#000000", "#ffcc00"]         # like --palette, or a theme name
+//  keys = { left = 4, right = 6, a = "F" }  # up, down, left, right, a, b
+const SHIPPED: &str = include_str!("../data/roms.toml");
+
+//what the database knows about one ROM. anything it leaves out is up to the command line and defaults
+#[derive(Clone, Debug, Default)]
+pub struct RomInfo {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub platform: Option<Platform>,
+    pub quirks: Option<RomQuirks>,
+    pub tickrate: Option<u32>,
+    pub palette: Option<Palette>,
+    //the CHIP-8 key behind each control the ROM uses
+    pub keys: Vec<(Control, usize)>,
+}
+
+//a preset replaces the platform's quirks, single quirks go on top of the quirks of whichever platform
+//ends up running the ROM, which --platform can change
+#[derive(Clone, Debug, PartialEq)]
+pub enum RomQuirks {
+    Preset(Quirks),
+    Single(Vec<(String, bool)>),
+}
+
+impl RomQuirks {
+    pub fn for_platform(&self, platform: Platform) -> Quirks {
+        match self {
+            RomQuirks::Preset(quirks) => *quirks,
+            RomQuirks::Single(single) => {
+                let mut quirks = platform.default_quirks();
+                for (name, enabled) in single {
+                    if let Some(quirk) = quirk(&mut quirks, name) {
+                        *quirk = *enabled;
+                    }
+                }
+                quirks
+            }
+        }
+    }
+}
+
+//the entry for `rom_hash`, shipped and user fields merged
+pub fn lookup(rom_hash: &str) -> Result<Option<RomInfo>, String> {
+    let shipped = find(&SHIPPED.parse::<Table>().expect("data/roms.toml is valid TOML"), rom_hash);
+    let path = config::config_dir().join("chip8rs").join("roms.toml");
+    let user = match path.exists() {
+        true => {
+            let text = fs::read_to_string(&path).map_err(|error| format!("could not read {}: {}", path.display(), error))?;
+            let table: Table = text.parse().map_err(|error: toml::de::Error| format!("{}: {}", path.display(), error))?;
+            find(&table, rom_hash)
+        }
+        false => None
+    };
+    if shipped.is_none() && user.is_none() {
+        return Ok(None);
+    }
+    let mut entry = shipped.unwrap_or_default();
+    entry.extend(user.unwrap_or_default());
+    parse_entry(&entry).map(Some).map_err(|error| format!("ROM database entry {}: {}", rom_hash, error))
+}
+
+fn find(table: &Table, rom_hash: &str) -> Option<Table> {
+    table.iter().find(|(hash, _)| hash.eq_ignore_ascii_case(rom_hash)).and_then(|(_, entry)| entry.as_table().cloned())
+}
+
+fn parse_entry(entry: &Table) -> Result<RomInfo, String> {
+    let mut info = RomInfo::default();
+    let string = |value: &Value, name: &str| value.as_str().map(str::to_string).ok_or(format!("'{}' should be a string", name));
+    for (name, value) in entry {
+        match name.as_str() {
+            "title" => info.title = Some(string(value, name)?),
+            "author" => info.author = Some(string(value, name)?),
+            "platform" => {
+                let platform = string(value, name)?;
+                info.platform = Some(Platform::from_name(&platform).ok_or(format!("unknown platform '{}'", platform))?);
+            }
+            "quirks" => info.quirks = Some(parse_quirks(value)?),
+            "tickrate" => {
+                let tickrate = value.as_integer().filter(|tickrate| (1..=100_000).contains(tickrate));
+                info.tickrate = Some(tickrate.ok_or("'tickrate' should be a number of instructions per frame")? as u32);
+            }
+            "colors" => {
+                info.palette = Some(config::parse_palette(value).ok_or("'colors' should be a theme name or a list of 2 to 5 hex colours")?);
+            }
+            "keys" => {
+                let keys = value.as_table().ok_or("'keys' should be a table of controls")?;
+                for (control, key) in keys {
+                    let control = Control::from_name(control).ok_or(format!("unknown control '{}', expected up, down, left, right, a or b", control))?;
+                    let key = parse_key(key).ok_or(format!("'keys.{}' should be a CHIP-8 key from 0 to 15", control.name()))?;
+                    info.keys.push((control, key));
+                }
+            }
+            _ => return Err(format!("unknown field '{}'", name))
+        }
+    }
+    Ok(info)
+}
+
+fn parse_quirks(value: &Value) -> Result<RomQuirks, String> {
+    if let Some(preset) = value.as_str() {
+        return Quirks::from_preset(preset).map(RomQuirks::Preset).ok_or(format!("unknown quirks preset '{}'", preset));
+    }
+    let table = value.as_table().ok_or("'quirks' should be a preset name or a table of quirks")?;
+    let mut single = Vec::new();
+    for (name, value) in table {
+        let enabled = value.as_bool().ok_or(format!("'quirks.{}' should be true or false", name))?;
+        quirk(&mut Quirks::default(), name).ok_or(format!("unknown quirk '{}'", name))?;
+        single.push((name.clone(), enabled));
+    }
+    Ok(RomQuirks::Single(single))
+}
+
+fn quirk<'a>(quirks: &'a mut Quirks, name: &str) -> Option<&'a mut bool> {
+    match name {
+        "shift_uses_vy" => Some(&mut quirks.shift_uses_vy),
+        "load_store_increments_i" => Some(&mut quirks.load_store_increments_i),
+        "jump_uses_vx" => Some(&mut quirks.jump_uses_vx),
+        "logic_resets_vf" => Some(&mut quirks.logic_resets_vf),
+        "wrap_sprites" => Some(&mut quirks.wrap_sprites),
+        "display_wait" => Some(&mut quirks.display_wait),
+        _ => None
+    }
+}
+
+//"up 5, left 7, a 6" for the console
+pub fn describe_keys(keys: &[(Control, usize)]) -> String {
+    keys.iter().map(|(control, key)| format!("{} {:X}", control.name(), key)).collect::<Vec<_>>().join(", ")
+}
+
+//a number from 0 to 15, or "0"-"F" as in config.toml
+fn parse_key(value: &Value) -> Option<usize> {
+    value.as_integer().filter(|key| (0..16).contains(key)).map(|key| key as usize)
+        .or_else(|| value.as_str().and_then(parse_chip8_key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(text: &str) -> Result<RomInfo, String> {
+        parse_entry(&text.parse::<Table>().unwrap())
+    }
+
+    #[test]
+    fn shipped_entries_parse() {
+        let shipped: Table = SHIPPED.parse().unwrap();
+        for (hash, entry) in &shipped {
+            assert!(hash.len() == 40 && hash.chars().all(|c| c.is_ascii_hexdigit()), "{}", hash);
+            parse_entry(entry.as_table().unwrap()).unwrap_or_else(|error| panic!("{}: {}", hash, error));
+        }
+    }
+
+    #[test]
+    fn every_field() {
+        let info = entry(r##"
+            title = "Brix"
+            author = "Andreas Gustafsson"
+            platform = "schip"
+            quirks = "vip"
+            tickrate = 15
+            colors = ["#000000", "ffcc00"]
+            keys = { left = 4, right = "6", a = "F" }
+        "##).unwrap();
+        assert_eq!((info.title.as_deref(), info.author.as_deref()), (Some("Brix"), Some("Andreas Gustafsson")));
+        assert_eq!(info.platform, Some(Platform::SuperChip));
+        assert_eq!(info.quirks, Some(RomQuirks::Preset(Quirks::vip())));
+        assert_eq!(info.tickrate, Some(15));
+        assert_eq!(info.palette, Palette::parse("#000000,ffcc00"));
+        assert_eq!(info.keys, [(Control::A, 0xF), (Control::Left, 0x4), (Control::Right, 0x6)]);
+        assert_eq!(describe_keys(&info.keys), "a F, left 4, right 6");
+    }
+
+    #[test]
+    fn single_quirks_go_on_the_platform_in_effect() {
+        let info = entry("platform = \"chip8\"\nquirks = { wrap_sprites = true, display_wait = false }").unwrap();
+        let quirks = info.quirks.unwrap();
+        let vip = Quirks { wrap_sprites: true, display_wait: false, ..Quirks::vip() };
+        assert_eq!(quirks.for_platform(Platform::Chip8), vip);
+        //--platform schip starts from SUPER-CHIP's quirks instead
+        assert_eq!(quirks.for_platform(Platform::SuperChip), Quirks { wrap_sprites: true, ..Quirks::schip() });
+        //a preset doesn't depend on the platform
+        assert_eq!(RomQuirks::Preset(Quirks::chip48()).for_platform(Platform::XoChip), Quirks::chip48());
+    }
+
+    #[test]
+    fn bad_fields_are_named() {
+        for (text, error) in [
+            ("title = 1", "'title' should be a string"),
+            ("platform = \"nes\"", "unknown platform 'nes'"),
+            ("quirks = \"nes\"", "unknown quirks preset 'nes'"),
+            ("quirks = 1", "'quirks' should be a preset name or a table of quirks"),
+            ("quirks = { wrap_sprites = 1 }", "'quirks.wrap_sprites' should be true or false"),
+            ("quirks = { fast = true }", "unknown quirk 'fast'"),
+            ("tickrate = 0", "'tickrate' should be a number of instructions per frame"),
+            ("colors = \"plaid\"", "'colors' should be a theme name or a list of 2 to 5 hex colours"),
+            ("keys = 1", "'keys' should be a table of controls"),
+            ("keys = { jump = 5 }", "unknown control 'jump', expected up, down, left, right, a or b"),
+            ("keys = { up = 16 }", "'keys.up' should be a CHIP-8 key from 0 to 15"),
+            ("keys = { up = \"G\" }", "'keys.up' should be a CHIP-8 key from 0 to 15"),
+            ("year = 1990", "unknown field 'year'"),
+        ] {
+            assert_eq!(entry(text).unwrap_err(), error, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use std::{env, fs, io};
+use std::path::PathBuf;
+use chip8rs_core::Processor;
+
+//numbered slots live in <data dir>/chip8rs/states/<rom sha1>/slot<N>.state
+pub fn slot_path(rom_hash: &str, slot: u8) -> PathBuf {
+    data_dir().join("chip8rs").join("states").join(rom_hash).join(format!("slot{}.state", slot))
+}
+
+pub fn save_slot(chip: &Processor, rom_hash: &str, slot: u8) -> io::Result<PathBuf> {
+    let path = slot_path(rom_hash, slot);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, chip.save_state())?;
+    Ok(path)
+}
+
+pub fn load_file(chip: &mut Processor, path: &PathBuf) -> Result<(), String> {
+    let data = fs::read(path).map_err(|error| format!("could not read {}: {}", path.display(), error))?;
+    chip.load_state(&data).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+pub fn load_slot(chip: &mut Processor, rom_hash: &str, slot: u8) -> Result<PathBuf, String> {
+    let path = slot_path(rom_hash, slot);
+    load_file(chip, &path)?;
+    Ok(path)
+}
+
+fn data_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("XDG_DATA_HOME").or_else(|| env::var_os("APPDATA")) {
+        return PathBuf::from(dir);
+    }
+    match env::var_os("HOME") {
+        Some(home) => PathBuf::from(home).join(".local").join("share"),
+        None => PathBuf::from(".")
+    }
+}